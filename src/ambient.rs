@@ -0,0 +1,28 @@
+use crate::metaball::Metaball;
+
+/// Number of procedural balls drifting around when ambient mode is on.
+pub const AMBIENT_BALL_COUNT: usize = 4;
+
+/// Smooth noise in [-1, 1] from a few incommensurate sines, so the drift
+/// never visibly repeats.
+fn drift(seed: f32, time: f32) -> f32 {
+    (time * 0.13 + seed * 1.7).sin() * 0.5
+        + (time * 0.071 + seed * 3.1).sin() * 0.3
+        + (time * 0.037 + seed * 5.3).sin() * 0.2
+}
+
+/// The ambient balls at effect time `time` seconds, slowly wandering around
+/// the center of the region.
+pub fn ambient_balls(time: f64) -> Vec<Metaball> {
+    let time = time as f32;
+    (0..AMBIENT_BALL_COUNT)
+        .map(|i| {
+            let seed = i as f32;
+            Metaball::new(
+                0.5 + drift(seed, time) * 0.3,
+                0.5 + drift(seed + 0.5, time) * 0.3,
+                0.08 + (drift(seed + 0.25, time) + 1.0) * 0.02,
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,59 @@
+use crate::metaball::Metaball;
+
+/// An analyser driving the ball radii, with the buffers reused every frame.
+pub struct Analyser {
+    /// The node and the array its data is read into, None for fixed bins.
+    node: Option<(web_sys::AnalyserNode, js_sys::Uint8Array)>,
+    pub bands: usize,
+    data: Vec<u8>,
+}
+
+impl Analyser {
+    pub fn new(node: web_sys::AnalyserNode, bands: usize) -> Analyser {
+        let bin_count = node.frequency_bin_count();
+        Analyser {
+            node: Some((node, js_sys::Uint8Array::new_with_length(bin_count))),
+            bands,
+            data: vec![0; bin_count as usize],
+        }
+    }
+
+    /// An analyser reading the same frequency `bins` every frame, standing in
+    /// for a node without audio.
+    pub fn with_bins(bins: Vec<u8>, bands: usize) -> Analyser {
+        Analyser { node: None, bands, data: bins }
+    }
+
+    pub fn read_levels(&mut self) -> Vec<f32> {
+        if let Some((node, array)) = &self.node {
+            node.get_byte_frequency_data_with_u8_array(array);
+            array.copy_to(&mut self.data);
+        }
+        band_levels(&self.data, self.bands)
+    }
+}
+
+/// Averages the frequency bins into `bands` equal groups, each in [0, 1].
+pub fn band_levels(bins: &[u8], bands: usize) -> Vec<f32> {
+    if bands == 0 || bins.is_empty() {
+        return vec![];
+    }
+
+    (0..bands).map(|band| {
+        let start = band * bins.len() / bands;
+        let end = ((band + 1) * bins.len() / bands).max(start + 1).min(bins.len());
+        let sum: u32 = bins[start..end].iter().map(|&bin| bin as u32).sum();
+        sum as f32 / (end - start) as f32 / 255.0
+    }).collect()
+}
+
+/// Scales each ball's radius by the level of its band; band `i % bands`
+/// drives ball `i`. Silence halves the radius and a full band makes it 1.5x.
+pub fn apply_levels(balls: &[Metaball], levels: &[f32]) -> Vec<Metaball> {
+    balls.iter().enumerate().map(|(i, ball)| {
+        match levels.len() {
+            0 => *ball,
+            len => Metaball { radius: ball.radius * (0.5 + levels[i % len]), ..*ball },
+        }
+    }).collect()
+}
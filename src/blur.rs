@@ -0,0 +1,19 @@
+/// Number of taps on each side of the center the blur shader samples at most.
+pub const MAX_BLUR_TAPS: usize = 32;
+
+/// One-sided weights of a normalized Gaussian kernel covering `radius`
+/// pixels, with the center weight first. The kernel is symmetric, so every
+/// weight but the center one applies on both sides. Empty for no blur.
+pub fn gaussian_weights(radius: f32) -> Vec<f32> {
+    if radius.is_nan() || radius < 1.0 {
+        return vec![];
+    }
+    let taps = (radius.ceil() as usize + 1).min(MAX_BLUR_TAPS);
+    let sigma = (taps - 1) as f32 / 2.0;
+
+    let weights: Vec<f32> = (0..taps)
+        .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+    weights.iter().map(|weight| weight / sum).collect()
+}
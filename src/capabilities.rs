@@ -0,0 +1,25 @@
+use wasm_bindgen::prelude::*;
+
+/// What the WebGL context turned out to support.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Capabilities {
+    context_id: String,
+    /// Bits of fragment shader highp float precision, 0 when unsupported.
+    pub highp_precision: i32,
+}
+
+impl Capabilities {
+    pub fn new(context_id: String, highp_precision: i32) -> Capabilities {
+        Capabilities { context_id, highp_precision }
+    }
+}
+
+#[wasm_bindgen]
+impl Capabilities {
+    /// The context identifier `getContext` succeeded with.
+    #[wasm_bindgen(getter)]
+    pub fn context_id(&self) -> String {
+        self.context_id.clone()
+    }
+}
@@ -0,0 +1,86 @@
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Strategy {
+    Full,
+    Fade { alpha: f32 },
+    Preserve,
+    Trail { decay: f32 },
+}
+
+/// How the previous frame is treated before the field is drawn. Only one
+/// strategy can be active at a time.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClearStrategy {
+    strategy: Strategy,
+}
+
+/// What the render loop does for a frame before drawing the field.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameClear {
+    /// Clear the whole drawing buffer to the clear color.
+    pub clear: bool,
+    /// Opacity of the clear color drawn over the previous frame, 0 for none.
+    pub fade_alpha: f32,
+    /// Draw the field with a transparent background, blending it over the
+    /// previous frame.
+    pub blend: bool,
+}
+
+#[wasm_bindgen]
+impl ClearStrategy {
+    /// Clears every frame.
+    pub fn full() -> ClearStrategy {
+        ClearStrategy { strategy: Strategy::Full }
+    }
+
+    /// Covers the previous frame with the clear color at `alpha` opacity
+    /// every frame.
+    pub fn fade(alpha: f32) -> ClearStrategy {
+        ClearStrategy { strategy: Strategy::Fade { alpha: alpha.clamp(0.0, 1.0) } }
+    }
+
+    /// Never clears, so the blobs smear permanently.
+    pub fn preserve() -> ClearStrategy {
+        ClearStrategy { strategy: Strategy::Preserve }
+    }
+
+    /// Like `fade`, but the previous frames decay at `decay` per second
+    /// regardless of the frame rate.
+    pub fn trail(decay: f32) -> ClearStrategy {
+        ClearStrategy { strategy: Strategy::Trail { decay: decay.max(0.0) } }
+    }
+}
+
+impl ClearStrategy {
+    /// The operations for a frame `dt` seconds after the previous one.
+    pub fn frame(&self, dt: f32) -> FrameClear {
+        match self.strategy {
+            Strategy::Full => FrameClear { clear: true, fade_alpha: 0.0, blend: false },
+            Strategy::Fade { alpha } => FrameClear { clear: false, fade_alpha: alpha, blend: true },
+            Strategy::Preserve => FrameClear { clear: false, fade_alpha: 0.0, blend: true },
+            Strategy::Trail { decay } => FrameClear {
+                clear: false,
+                fade_alpha: 1.0 - (-decay * dt.max(0.0)).exp(),
+                blend: true,
+            },
+        }
+    }
+
+    /// The JS expression constructing this strategy.
+    pub fn to_code(&self) -> String {
+        match self.strategy {
+            Strategy::Full => "ClearStrategy.full()".to_string(),
+            Strategy::Fade { alpha } => format!("ClearStrategy.fade({})", alpha),
+            Strategy::Preserve => "ClearStrategy.preserve()".to_string(),
+            Strategy::Trail { decay } => format!("ClearStrategy.trail({})", decay),
+        }
+    }
+}
+
+impl Default for ClearStrategy {
+    fn default() -> ClearStrategy {
+        ClearStrategy::full()
+    }
+}
@@ -0,0 +1,156 @@
+use std::cell::Cell;
+use wasm_bindgen::prelude::*;
+
+use crate::easing::{ease_in_out, ease_toward};
+
+thread_local! {
+    /// The wall clock time injected in test mode, None outside of it.
+    static TEST_TIME: Cell<Option<f64>> = const { Cell::new(None) };
+}
+
+/// Makes `current_time` return an injected time instead of reading the wall
+/// clock, starting at 0 and moved by `set_test_time`, so that the whole
+/// effect runs deterministically. Pointer event timestamps are replaced by
+/// it too; randomness is always seeded, see `set_seed`.
+#[wasm_bindgen]
+pub fn set_test_mode(enabled: bool) {
+    TEST_TIME.with(|time| time.set(if enabled { Some(0.0) } else { None }));
+}
+
+/// Moves the injected time of test mode forward to `seconds`. Earlier times
+/// are ignored, and so is the call outside of test mode.
+#[wasm_bindgen]
+pub fn set_test_time(seconds: f64) {
+    TEST_TIME.with(|time| {
+        if let Some(current) = time.get() {
+            time.set(Some(current.max(seconds)));
+        }
+    });
+}
+
+/// The injected time, while in test mode.
+pub fn test_time() -> Option<f64> {
+    TEST_TIME.with(|time| time.get())
+}
+
+/// The wall clock in seconds, `Date.now() / 1000` outside of test mode.
+pub fn current_time() -> f64 {
+    test_time().unwrap_or_else(|| js_sys::Date::now() / 1000.0)
+}
+
+/// How fast the freeze-on-hover speed factor follows the hover state, in 1/seconds.
+const HOVER_EASE_RATE: f32 = 4.0;
+
+/// Wall clock gaps between frames longer than this, in seconds, are taken as
+/// the page having been throttled or hidden rather than as a slow frame.
+pub const MAX_FRAME_GAP: f64 = 1.0;
+
+/// Effect time, advanced by scaled wall clock deltas so that changing the
+/// speed never makes the time uniform jump.
+pub struct Clock {
+    last: f64,
+    elapsed: f64,
+    /// Synced to a time origin shared with other clocks.
+    synced: bool,
+}
+
+impl Clock {
+    pub fn new(now: f64) -> Clock {
+        Clock { last: now, elapsed: 0.0, synced: false }
+    }
+
+    /// Returns the wall clock seconds since the previous tick.
+    pub fn tick(&mut self, now: f64) -> f64 {
+        let dt = now - self.last;
+        self.last = now;
+        dt
+    }
+
+    pub fn advance(&mut self, dt: f64) {
+        self.elapsed += dt;
+    }
+
+    /// The wall clock time of the last tick.
+    pub fn now(&self) -> f64 {
+        self.last
+    }
+
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    /// Restarts the elapsed time as if it had been counting since wall clock
+    /// time `origin`. Clocks synced to the same origin stay in phase while
+    /// they run at the same speed.
+    pub fn sync_to_origin(&mut self, origin: f64) {
+        self.elapsed = self.last - origin;
+        self.synced = true;
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.synced
+    }
+}
+
+/// Eases the speed toward 0 while the cursor is over the canvas.
+pub struct HoverFreeze {
+    pub enabled: bool,
+    pub hovered: bool,
+    factor: f32,
+}
+
+impl Default for HoverFreeze {
+    fn default() -> HoverFreeze {
+        HoverFreeze { enabled: false, hovered: false, factor: 1.0 }
+    }
+}
+
+impl HoverFreeze {
+    pub fn new() -> HoverFreeze {
+        HoverFreeze::default()
+    }
+
+    /// Returns the speed factor after `dt` seconds.
+    pub fn update(&mut self, dt: f32) -> f32 {
+        let target = if self.enabled && self.hovered { 0.0 } else { 1.0 };
+        self.factor = ease_toward(self.factor, target, HOVER_EASE_RATE, dt);
+        self.factor
+    }
+}
+
+/// An eased transition of the speed multiplier.
+pub struct SpeedTween {
+    from: f32,
+    to: f32,
+    duration: f32,
+    progress: f32,
+}
+
+impl SpeedTween {
+    pub fn new(from: f32, to: f32, duration: f32) -> SpeedTween {
+        SpeedTween { from, to, duration, progress: 0.0 }
+    }
+
+    pub fn value(&self) -> f32 {
+        if self.duration <= 0.0 {
+            return self.to;
+        }
+        self.from + (self.to - self.from) * ease_in_out(self.progress / self.duration)
+    }
+
+    /// Advances by `dt` seconds and returns the mean speed over the step, so
+    /// that the effect time integrated from it has no discontinuity.
+    pub fn advance(&mut self, dt: f32) -> f32 {
+        let before = self.value();
+        self.progress = (self.progress + dt).min(self.duration.max(0.0));
+        (before + self.value()) / 2.0
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.progress >= self.duration
+    }
+
+    pub fn target(&self) -> f32 {
+        self.to
+    }
+}
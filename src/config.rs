@@ -0,0 +1,68 @@
+use wasm_bindgen::prelude::*;
+
+/// Where the pointer listeners are attached.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PointerTarget {
+    Canvas,
+    Document,
+    Window,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub pointer_target: PointerTarget,
+    /// Adds noise below one 8-bit step to the output to hide banding.
+    pub dithering: bool,
+    context_ids: Vec<String>,
+    /// Listens to pointermove instead of mousemove and replays the events
+    /// the browser coalesced into each of them.
+    pub coalesce_pointer_events: bool,
+    /// Captures the pointer while a button is held on the canvas, so drags
+    /// keep updating it outside the canvas.
+    pub capture_pointer: bool,
+}
+
+#[wasm_bindgen]
+impl Config {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Config {
+        Config::default()
+    }
+
+    /// Context identifiers tried in order until `getContext` succeeds.
+    pub fn set_context_ids(&mut self, ids: Vec<String>) {
+        self.context_ids = ids;
+    }
+}
+
+impl Config {
+    pub fn context_ids(&self) -> &[String] {
+        &self.context_ids
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            pointer_target: PointerTarget::Canvas,
+            dithering: false,
+            context_ids: vec!["webgl2".into(), "webgl".into(), "experimental-webgl".into()],
+            coalesce_pointer_events: false,
+            capture_pointer: true,
+        }
+    }
+}
+
+/// Enables dithering when the fragment shader has no highp support
+/// (`highp_precision` is the bit count reported by getShaderPrecisionFormat,
+/// which is 0 in that case). The scene shader then runs at mediump on its
+/// own, so this only adds the dithering. Returns whether it was applied.
+pub fn apply_precision_fallback(config: &mut Config, highp_precision: i32) -> bool {
+    if highp_precision > 0 || config.dithering {
+        return false;
+    }
+    config.dithering = true;
+    true
+}
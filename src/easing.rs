@@ -0,0 +1,20 @@
+/// Moves `current` toward `target` exponentially, frame rate independently.
+/// `rate` is the inverse time constant in 1/seconds.
+pub fn ease_toward(current: f32, target: f32, rate: f32, dt: f32) -> f32 {
+    target + (current - target) * (-rate * dt).exp()
+}
+
+/// Opacity `elapsed` seconds into a fade-in lasting `duration`, easing from
+/// 0 to 1. Always 1 without a duration.
+pub fn fade_in_opacity(elapsed: f32, duration: f32) -> f32 {
+    if duration <= 0.0 {
+        return 1.0;
+    }
+    ease_in_out(elapsed / duration)
+}
+
+/// Smoothstep easing of `t` in [0, 1], with zero slope at both ends.
+pub fn ease_in_out(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
@@ -0,0 +1,1269 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use std::rc::{Rc};
+use std::cell::{RefCell};
+
+use crate::ambient;
+use crate::audio::{self, Analyser};
+use crate::capabilities::Capabilities;
+use crate::clear::ClearStrategy;
+use crate::clock::{Clock, HoverFreeze, SpeedTween, MAX_FRAME_GAP};
+use crate::config::Config;
+use crate::export;
+use crate::field::{self, FieldComputer};
+use crate::geometry::CullMode;
+use crate::isolines;
+use crate::layers::{self, Layer};
+use crate::metaball::{self, Lifetime, Metaball, Shape, ShapeKind};
+use crate::origin::{self, Origin};
+use crate::paint::Paint;
+use crate::params::{self, MAX_PARAMS};
+use crate::physics;
+use crate::easing::{self, ease_toward};
+use crate::emitter::{self, Emitter, Rng};
+use crate::pointer::{self, PointerTracker};
+use crate::ripple::{RippleParams, Ripples};
+use crate::pulse::{self, ColorPulse};
+use crate::smoothing::{PointerFilter, Smoothing, SmoothingKind};
+use crate::stats::{FrameStats, RenderStats};
+use crate::theme::{self, Palette};
+use crate::tail::PointerTail;
+use crate::tiles::{MAX_TILED_METABALLS, MAX_TILES_PER_SIDE};
+use crate::upscale;
+use crate::viewport::{self, Region};
+
+pub(crate) struct State {
+    pub config: Config,
+    pub balls: Vec<Metaball>,
+    pub analyser: Option<Analyser>,
+    pub padding: f32,
+    /// Width / height the region is letterboxed to, 0 to fill the canvas.
+    pub target_aspect: f32,
+    /// Drawing buffer rectangle clears and draws are confined to.
+    pub scissor_rect: Option<Region>,
+    pub clock: Clock,
+    pub speed: f32,
+    pub speed_tween: Option<SpeedTween>,
+    pub hover_freeze: HoverFreeze,
+    pub on_warning: Option<js_sys::Function>,
+    pub ripples: Ripples,
+    pub physics_paused: bool,
+    pub max_physics_step: f32,
+    /// Restitution of the collisions between balls, None to let them overlap.
+    pub ball_collisions: Option<f32>,
+    pub clear_strategy: ClearStrategy,
+    /// Wall clock seconds between the last two updates.
+    pub frame_dt: f64,
+    pub frame_stats: FrameStats,
+    pub on_jank: Option<js_sys::Function>,
+    /// Called with the effect time at the start of each frame, returning
+    /// the parameters to apply.
+    pub on_update: Option<js_sys::Function>,
+    /// Tiles across and down when rendering in tiles.
+    pub tile_grid: Option<(u32, u32)>,
+    /// Some tiles had more balls than they can evaluate, which was warned
+    /// about once.
+    pub tiles_overflowing: bool,
+    pub origin: Origin,
+    /// Size of the rendered region in drawing buffer pixels, as of the last frame.
+    pub region_size: (f32, f32),
+    pub blur_radius: f32,
+    pub pointer: PointerTracker,
+    /// Values of the `params` uniform array.
+    pub params: Vec<f32>,
+    /// Lighting bands for cel shading, 0 or 1 when disabled.
+    pub cel_levels: u32,
+    /// Restores the GL state a frame changes once it's rendered.
+    pub preserve_gl_state: bool,
+    /// Smallest radius a ball is rendered with, in buffer pixels.
+    pub min_screen_radius: f32,
+    /// Fraction of the region's resolution the scene is rendered at.
+    pub resolution_scale: f32,
+    /// Thresholds the edges at full resolution when rendering below it.
+    pub crisp_edges: bool,
+    pub paint: Paint,
+    /// Gamma the output is corrected for.
+    pub gamma: f32,
+    /// k of the smooth minimum the balls merge with, 0 to add up the field.
+    pub merge_smoothness: f32,
+    /// Fills the outside of the blobs, leaving them as holes.
+    pub invert_field: bool,
+    /// Shades the field images logarithmically.
+    pub field_log_scale: bool,
+    pub cull_mode: CullMode,
+    /// Seconds the blobs take to fade in from the start.
+    pub fade_in: f32,
+    pub pulses: Vec<ColorPulse>,
+    /// Renders procedural balls while none are added.
+    pub ambient_default: bool,
+    /// Rim color, the fill color when None.
+    pub edge_color: Option<(f32, f32, f32)>,
+    /// Hour of the simulated day the palette follows.
+    pub time_of_day: Option<f32>,
+    /// Cells across and down balls are snapped to.
+    pub grid_snap: Option<(u32, u32)>,
+    /// Layers from bottom to top.
+    pub layer_order: Vec<Layer>,
+    /// The pointer in normalized coordinates of the rendered region, updated
+    /// before every frame.
+    pub pointer_position: (f32, f32),
+    pub pointer_tail: Option<PointerTail>,
+    /// Radius growth at the cursor and the distance it fades out over.
+    pub melt: Option<(f32, f32)>,
+    /// Fixed resolution the scene is rendered at and upscaled from without
+    /// smoothing.
+    pub pixel_art: Option<(u32, u32)>,
+    /// How much faster mouse movement grows the mouse-driven spheres.
+    pub mouse_speed_response: f32,
+    /// Smoothed speed of `pointer_position` in normalized coordinates per
+    /// second, and the position it was last measured at.
+    pub mouse_speed: f32,
+    last_pointer_position: (f32, f32),
+    /// Smooths `pointer_position` at the start of each update.
+    pub pointer_filter: PointerFilter,
+    pub emitters: Vec<Emitter>,
+    /// Ids of the canvases each frame is copied to.
+    pub mirror_ids: Vec<String>,
+    /// Radius of the balls spawned by clicks and drags, 0 to not spawn.
+    pub spawn_radius: f32,
+    /// Seconds after a spawn during which further ones are ignored.
+    pub spawn_cooldown: f32,
+    last_spawn: Option<f64>,
+    /// A press arrived since the last frame.
+    pub spawn_requested: bool,
+    /// The seed `rng` was last seeded with.
+    pub seed: u32,
+    pub rng: Rng,
+    /// The canvas rendered to, None when headless.
+    pub canvas: Option<web_sys::HtmlCanvasElement>,
+    /// Keyboard and wheel input applies while the canvas has focus.
+    pub require_focus: bool,
+    pub focused: bool,
+    /// The tabindex of the canvas was added by `set_require_focus`.
+    added_tabindex: bool,
+    /// The keyboard, wheel and focus listeners are on the canvas.
+    listening_for_focused_input: bool,
+}
+
+impl State {
+    pub fn new(now: f64, config: Config) -> State {
+        State {
+            config,
+            balls: vec![],
+            analyser: None,
+            padding: 0.0,
+            target_aspect: 0.0,
+            scissor_rect: None,
+            clock: Clock::new(now),
+            speed: 1.0,
+            speed_tween: None,
+            hover_freeze: HoverFreeze::new(),
+            on_warning: None,
+            ripples: Ripples::new(),
+            physics_paused: false,
+            max_physics_step: 1.0 / 30.0,
+            ball_collisions: None,
+            clear_strategy: ClearStrategy::default(),
+            frame_dt: 0.0,
+            frame_stats: FrameStats::new(),
+            on_jank: None,
+            on_update: None,
+            tile_grid: None,
+            tiles_overflowing: false,
+            origin: Origin::TopLeft,
+            region_size: (1.0, 1.0),
+            blur_radius: 0.0,
+            pointer: PointerTracker::new(),
+            params: Vec::new(),
+            cel_levels: 0,
+            preserve_gl_state: false,
+            min_screen_radius: 0.0,
+            resolution_scale: 1.0,
+            crisp_edges: false,
+            paint: Paint::new(),
+            gamma: 1.0,
+            merge_smoothness: 0.0,
+            invert_field: false,
+            field_log_scale: false,
+            cull_mode: CullMode::None,
+            fade_in: 0.0,
+            pulses: vec![],
+            ambient_default: false,
+            edge_color: None,
+            time_of_day: None,
+            grid_snap: None,
+            layer_order: layers::default_layer_order(),
+            pointer_position: (0.5, 0.5),
+            pointer_tail: None,
+            melt: None,
+            pixel_art: None,
+            mouse_speed_response: 0.0,
+            mouse_speed: 0.0,
+            last_pointer_position: (0.5, 0.5),
+            pointer_filter: PointerFilter::new(Smoothing::None),
+            emitters: vec![],
+            mirror_ids: vec![],
+            spawn_radius: 0.0,
+            spawn_cooldown: 0.0,
+            last_spawn: None,
+            spawn_requested: false,
+            seed: emitter::DEFAULT_SEED,
+            rng: Rng::new(emitter::DEFAULT_SEED),
+            canvas: None,
+            require_focus: false,
+            focused: false,
+            added_tabindex: false,
+            listening_for_focused_input: false,
+        }
+    }
+
+    /// Converts a position passed in through the API to normalized coordinates.
+    pub fn normalize_position(&self, x: f32, y: f32) -> (f32, f32) {
+        let (width, height) = self.region_size;
+        origin::to_normalized(self.origin, x, y, width, height)
+    }
+
+    /// The part of a `buffer_w` x `buffer_h` drawing buffer the effect is
+    /// rendered in, after the padding and the letterboxing.
+    pub fn region(&self, buffer_w: f32, buffer_h: f32) -> Region {
+        Region::padded(buffer_w, buffer_h, self.padding).letterboxed(self.target_aspect)
+    }
+
+    /// Renders to `canvas` and takes the size of its region right away, so
+    /// positions set before the first frame are converted against it.
+    pub fn attach_canvas(&mut self, canvas: web_sys::HtmlCanvasElement) {
+        self.canvas = Some(canvas);
+        self.sync_region_size();
+    }
+
+    /// Updates `region_size` from the canvas, if there is one. The render
+    /// loop does this every frame.
+    fn sync_region_size(&mut self) {
+        if let Some(canvas) = &self.canvas {
+            let region = self.region(canvas.width() as f32, canvas.height() as f32);
+            self.region_size = (region.width, region.height);
+        }
+    }
+
+    pub fn denormalize_position(&self, x: f32, y: f32) -> (f32, f32) {
+        let (width, height) = self.region_size;
+        origin::from_normalized(self.origin, x, y, width, height)
+    }
+
+    /// The colors of this frame: the time of day palette if one is set,
+    /// with the edge color overriding its rim.
+    pub fn palette(&self) -> Palette {
+        let mut palette = self.time_of_day.map_or(theme::DEFAULT_PALETTE, theme::time_of_day_palette);
+        if let Some(edge) = self.edge_color {
+            palette.edge = edge;
+        }
+        palette
+    }
+
+    /// The accent color of the running pulses and how far the output is
+    /// pulled toward it.
+    pub fn pulse(&self) -> ((f32, f32, f32), f32) {
+        pulse::combined_pulse(&self.pulses, self.clock.now())
+    }
+
+    pub fn opacity(&self) -> f32 {
+        easing::fade_in_opacity(self.clock.elapsed() as f32, self.fade_in)
+    }
+
+    pub fn mouse_ball_scale(&self) -> f32 {
+        pointer::speed_scale(self.mouse_speed, self.mouse_speed_response)
+    }
+
+    pub fn ball_capacity(&self) -> usize {
+        match self.tile_grid {
+            Some(_) => MAX_TILED_METABALLS,
+            None => metaball::MAX_METABALLS,
+        }
+    }
+
+    /// Spawns a ball of `spawn_radius` at normalized coordinates at wall
+    /// clock time `now`, unless spawning is off, the cooldown since the last
+    /// spawn hasn't elapsed or every slot is in use. Returns whether it did.
+    pub fn spawn_at(&mut self, x: f32, y: f32, now: f64) -> bool {
+        if self.spawn_radius <= 0.0 {
+            return false;
+        }
+        if self.last_spawn.is_some_and(|last| now - last < self.spawn_cooldown as f64) {
+            return false;
+        }
+        if self.balls.len() >= self.ball_capacity() {
+            return false;
+        }
+        self.balls.push(Metaball::new(x, y, self.spawn_radius));
+        self.last_spawn = Some(now);
+        true
+    }
+
+    /// Whether keyboard and wheel input applies now.
+    fn accepts_focused_input(&self) -> bool {
+        self.require_focus && self.focused
+    }
+
+    /// Handles a pressed `key`: Space pauses or resumes the physics. Returns
+    /// whether the key was used, which it only is while focus is required
+    /// and the canvas has it.
+    pub fn key_down(&mut self, key: &str) -> bool {
+        if !self.accepts_focused_input() {
+            return false;
+        }
+        match key {
+            " " => {
+                self.physics_paused = !self.physics_paused;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Handles the wheel scrolled by `delta_y` pixels, which changes the
+    /// speed, with the same gating as `key_down`.
+    pub fn wheel(&mut self, delta_y: f32) -> bool {
+        if !self.accepts_focused_input() {
+            return false;
+        }
+        self.speed = pointer::wheel_speed(self.speed, delta_y);
+        self.speed_tween = None;
+        true
+    }
+
+    /// Makes the canvas focusable while focus is required, unless it already
+    /// has a tabindex, and removes the tabindex again once it isn't.
+    pub fn sync_tabindex(&mut self) {
+        let Some(canvas) = &self.canvas else {
+            return;
+        };
+        if self.require_focus && !canvas.has_attribute("tabindex") {
+            let _ = canvas.set_attribute("tabindex", "0");
+            self.added_tabindex = true;
+        } else if !self.require_focus && self.added_tabindex {
+            let _ = canvas.remove_attribute("tabindex");
+            self.added_tabindex = false;
+        }
+    }
+
+    /// Passes `message` to the warning callback, if one is set.
+    pub fn warn(&self, message: &str) {
+        if let Some(on_warning) = &self.on_warning {
+            let _ = on_warning.call1(&JsValue::NULL, &JsValue::from(message));
+        }
+    }
+
+    /// Whether all `values` passed to `setter` are finite, warning about the
+    /// ignored call when they aren't.
+    fn accepts_finite(&self, setter: &str, values: &[f32]) -> bool {
+        if values.iter().all(|value| value.is_finite()) {
+            return true;
+        }
+        self.warn(&format!("Ignoring {} with non-finite values {:?}.", setter, values));
+        false
+    }
+
+    /// Advances the effect time and the simulation to `now` and returns the
+    /// elapsed effect time.
+    pub fn update(&mut self, now: f64) -> f64 {
+        let frame_dt = self.clock.tick(now);
+        // Resume after a gap as if a single frame had passed, so neither the
+        // time nor the balls jump ahead by the whole gap. The stats still see
+        // the stall.
+        let dt = if frame_dt > MAX_FRAME_GAP { self.frame_stats.expected_interval() } else { frame_dt };
+        self.frame_dt = dt;
+        if self.frame_stats.record(frame_dt) > 0 {
+            if let Some(on_jank) = &self.on_jank {
+                let _ = on_jank.call1(&JsValue::NULL, &JsValue::from(frame_dt * 1000.0));
+            }
+        }
+        let base_speed = match &mut self.speed_tween {
+            Some(tween) => {
+                let mean = tween.advance(dt as f32);
+                self.speed = tween.value();
+                if tween.is_done() {
+                    self.speed_tween = None;
+                }
+                mean
+            },
+            None => self.speed,
+        };
+        self.pointer_position = self.pointer_filter.filter(self.pointer_position, dt as f32);
+        if dt > 0.0 {
+            let (x, y) = self.pointer_position;
+            let (last_x, last_y) = self.last_pointer_position;
+            let speed = (x - last_x).hypot(y - last_y) / dt as f32;
+            self.mouse_speed = ease_toward(self.mouse_speed, speed, pointer::MOUSE_SPEED_SMOOTHING, dt as f32);
+        }
+        self.last_pointer_position = self.pointer_position;
+        if let Some(tail) = &mut self.pointer_tail {
+            tail.update(self.pointer_position, dt as f32);
+        }
+        if std::mem::take(&mut self.spawn_requested) || self.pointer.pressed {
+            let (x, y) = self.pointer_position;
+            self.spawn_at(x, y, now);
+        }
+        // Lifetimes run on the wall clock, whatever the speed.
+        self.balls.retain_mut(|ball| ball.lifetime.as_mut().is_none_or(|lifetime| lifetime.tick(dt as f32)));
+        let capacity = self.ball_capacity();
+        for emitter in &mut self.emitters {
+            // Spawns beyond the capacity are dropped.
+            let free = capacity.saturating_sub(self.balls.len());
+            self.balls.extend(emitter.emit(dt as f32, free, &mut self.rng));
+        }
+        let speed = base_speed * self.hover_freeze.update(dt as f32);
+        // A clock sharing an origin counts through gaps, which the clamp
+        // above would cut differently on each instance.
+        let time_dt = if self.clock.is_synced() { frame_dt } else { dt };
+        self.clock.advance(time_dt * speed as f64);
+        let dt = dt * speed as f64;
+        self.pulses.retain(|pulse| !pulse.is_done(now));
+
+        if !self.physics_paused {
+            physics::integrate_with_collisions(&mut self.balls, dt as f32, self.max_physics_step, self.ball_collisions);
+        }
+
+        self.clock.elapsed()
+    }
+
+    /// Balls as they are uploaded this frame.
+    pub fn frame_balls(&mut self) -> Vec<Metaball> {
+        let mut balls = self.sized_balls();
+        if let Some((cells_x, cells_y)) = self.grid_snap {
+            for ball in &mut balls {
+                let (x, y) = viewport::snap_to_grid(ball.x, ball.y, cells_x, cells_y);
+                ball.x = x;
+                ball.y = y;
+            }
+        }
+        balls
+    }
+
+    /// The balls, or the ambient ones, with this frame's radii.
+    fn sized_balls(&mut self) -> Vec<Metaball> {
+        let tail = self.pointer_tail.as_ref().map_or(vec![], |tail| tail.balls());
+        if self.balls.is_empty() && self.ambient_default {
+            return [ambient::ambient_balls(self.clock.elapsed()), tail].concat();
+        }
+        let mut balls = match &mut self.analyser {
+            Some(analyser) => audio::apply_levels(&self.balls, &analyser.read_levels()),
+            None => self.balls.clone(),
+        };
+        for ball in &mut balls {
+            if let Some(lifetime) = ball.lifetime {
+                ball.radius *= lifetime.fade();
+            }
+        }
+        if let Some((strength, reach)) = self.melt {
+            let (width, height) = self.region_size;
+            let shorter = width.min(height);
+            let (pointer_x, pointer_y) = self.pointer_position;
+            for ball in &mut balls {
+                let distance = ((ball.x - pointer_x) * width / shorter).hypot((ball.y - pointer_y) * height / shorter);
+                ball.radius *= metaball::melt_factor(distance, strength, reach);
+            }
+        }
+        balls.extend(tail);
+        if self.min_screen_radius > 0.0 {
+            // measured in the pixels the scene is rendered at, before upscaling
+            let (width, height) = self.region_size;
+            let shorter = match upscale::render_size(width, height, self.resolution_scale, self.pixel_art) {
+                Some((width, height)) => width.min(height) as f32,
+                None => width.min(height),
+            };
+            for ball in &mut balls {
+                ball.radius = metaball::effective_radius(ball.radius, self.min_screen_radius, shorter);
+            }
+        }
+        balls
+    }
+}
+
+/// Handle to a running effect.
+#[wasm_bindgen]
+pub struct Effect {
+    state: Rc<RefCell<State>>,
+    capabilities: Capabilities,
+}
+
+impl Effect {
+    pub(crate) fn new(state: Rc<RefCell<State>>, capabilities: Capabilities) -> Effect {
+        Effect { state, capabilities }
+    }
+
+    /// The state, for the listeners that feed it.
+    pub(crate) fn shared_state(&self) -> Rc<RefCell<State>> {
+        self.state.clone()
+    }
+
+    /// An effect that isn't attached to a canvas, for driving the state
+    /// without WebGL. It behaves like a square canvas.
+    pub fn headless(config: Config) -> Effect {
+        let state = Rc::new(RefCell::new(State::new(0.0, config)));
+        Effect::new(state, Capabilities::new(String::new(), 0))
+    }
+
+    /// Runs a frame's update at wall clock time `now`, as the render loop
+    /// does, and returns the elapsed effect time.
+    pub fn update(&mut self, now: f64) -> f64 {
+        self.run_on_update();
+        self.state.borrow_mut().update(now)
+    }
+
+    /// The balls, in normalized canvas coordinates.
+    pub fn balls(&self) -> Vec<Metaball> {
+        self.state.borrow().balls.clone()
+    }
+
+    pub fn layer_order(&self) -> Vec<Layer> {
+        self.state.borrow().layer_order.clone()
+    }
+
+    /// The balls as they are uploaded for the next frame.
+    pub fn frame_balls(&self) -> Vec<Metaball> {
+        self.state.borrow_mut().frame_balls()
+    }
+
+    /// The CPU field of the current balls over the rendered region.
+    pub fn field_computer(&self) -> FieldComputer {
+        let state = self.state.borrow();
+        let (width, height) = state.region_size;
+        FieldComputer::new(&state.balls, width, height)
+            .with_merge_smoothness(state.merge_smoothness)
+            .with_inverted(state.invert_field)
+            .with_log_scale(state.field_log_scale)
+    }
+
+    /// The values of the `params` uniform array as uploaded each frame.
+    pub fn params(&self) -> Vec<f32> {
+        params::params_uniform(&self.state.borrow().params)
+    }
+
+    /// The colors the next frame is drawn in.
+    pub fn palette(&self) -> Palette {
+        self.state.borrow().palette()
+    }
+
+    /// The accent color the output is pulled toward this frame and how far.
+    pub fn pulse(&self) -> ((f32, f32, f32), f32) {
+        self.state.borrow().pulse()
+    }
+
+    /// The opacity the blobs are drawn at this frame.
+    pub fn opacity(&self) -> f32 {
+        self.state.borrow().opacity()
+    }
+
+    /// The radius factor of the default mouse-driven spheres.
+    pub fn mouse_ball_scale(&self) -> f32 {
+        self.state.borrow().mouse_ball_scale()
+    }
+
+    /// Spawns a ball like a click at normalized coordinates would at wall
+    /// clock time `now`, returning whether one was added.
+    pub fn spawn_at(&mut self, x: f32, y: f32, now: f64) -> bool {
+        self.state.borrow_mut().spawn_at(x, y, now)
+    }
+
+    /// Listens for the keyboard, wheel and focus events of the canvas once
+    /// focus is required, unless it already does or there's no canvas yet.
+    fn listen_to_focused_input(&self) {
+        let canvas = {
+            let state = self.state.borrow();
+            if !state.require_focus || state.listening_for_focused_input {
+                return;
+            }
+            match &state.canvas {
+                Some(canvas) => canvas.clone(),
+                None => return,
+            }
+        };
+        let listening = crate::listen_to_focused_input(&self.state, &canvas).is_ok();
+        let mut state = self.state.borrow_mut();
+        state.listening_for_focused_input = listening;
+        if !listening {
+            state.warn("Couldn't listen to the keyboard and wheel on the canvas.");
+        }
+    }
+
+    /// Presses `key` as the keyboard listener does, returning whether it was
+    /// used.
+    pub fn key_down(&mut self, key: &str) -> bool {
+        self.state.borrow_mut().key_down(key)
+    }
+
+    /// Tells the headless effect whether its canvas has focus.
+    pub fn set_focused(&mut self, focused: bool) {
+        self.state.borrow_mut().focused = focused;
+    }
+
+    /// Drives the ball radii like `connect_analyser` from frequency `bins`
+    /// that stay the same every frame.
+    pub fn connect_bins(&mut self, bins: Vec<u8>, bands: usize) {
+        self.state.borrow_mut().analyser = Some(Analyser::with_bins(bins, bands));
+    }
+
+    /// The pointer samples received from the listeners, in CSS pixels.
+    pub fn pointer(&self) -> PointerTracker {
+        self.state.borrow().pointer
+    }
+
+    /// The pointer position the effect follows, after smoothing.
+    pub fn pointer_position(&self) -> (f32, f32) {
+        self.state.borrow().pointer_position
+    }
+
+    /// Moves the pointer of the headless effect, in normalized coordinates.
+    pub fn set_pointer_position(&mut self, x: f32, y: f32) {
+        self.state.borrow_mut().pointer_position = (x, y);
+    }
+
+    /// Attaches the headless effect to `canvas`, as starting it does.
+    pub fn attach_canvas(&mut self, canvas: web_sys::HtmlCanvasElement) {
+        self.state.borrow_mut().attach_canvas(canvas);
+        self.listen_to_focused_input();
+    }
+
+    /// Sets the size the headless effect converts positions against.
+    pub fn set_region_size(&mut self, width: f32, height: f32) {
+        self.state.borrow_mut().region_size = (width, height);
+    }
+}
+
+#[wasm_bindgen]
+impl Effect {
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities.clone()
+    }
+
+    /// A JS snippet of the calls reproducing the current configuration.
+    pub fn export_as_code(&self) -> String {
+        export::export_as_code(&self.state.borrow())
+    }
+
+    /// Adds a ball at a position interpreted according to `set_origin`,
+    /// normalized canvas coordinates by default. While no balls are added,
+    /// the default pair of mouse-driven spheres is rendered. Fails for a
+    /// radius that isn't positive or a position that isn't finite, and once
+    /// `MAX_METABALLS` balls exist, or `MAX_TILED_METABALLS` when rendering
+    /// in tiles.
+    pub fn add_metaball(&mut self, x: f32, y: f32, radius: f32) -> Result<(), JsValue> {
+        self.add_metaball_shape(x, y, radius, ShapeKind::Circle, 0.0, 0.0)
+    }
+
+    /// Adds a ball like `add_metaball` with a `kind` of outline: an ellipse
+    /// with radii `a` and `b`, which replace `radius`, or a capsule of length
+    /// `a` rotated by `b` radians. Ellipses and capsules cost a few more
+    /// operations per pixel than circles.
+    pub fn add_metaball_shape(&mut self, x: f32, y: f32, radius: f32, kind: ShapeKind, a: f32, b: f32) -> Result<(), JsValue> {
+        let mut state = self.state.borrow_mut();
+        let capacity = state.ball_capacity();
+        let shape = Shape::from_kind(kind, a, b);
+        if let Err(message) = metaball::check_ball(x, y, radius, shape)
+            .and_then(|_| metaball::check_capacity(state.balls.len(), 1, capacity))
+        {
+            state.warn(&message);
+            return Err(JsValue::from(&message));
+        }
+        let (x, y) = state.normalize_position(x, y);
+        state.balls.push(Metaball::with_shape(x, y, radius, shape));
+        Ok(())
+    }
+
+    /// Adds a ball that fades out over the last quarter of
+    /// `lifetime_seconds` and is then removed, freeing its slot. The lifetime
+    /// counts down in real time, whatever the speed.
+    pub fn add_metaball_with_lifetime(&mut self, x: f32, y: f32, radius: f32, lifetime_seconds: f32) -> Result<(), JsValue> {
+        self.add_metaball(x, y, radius)?;
+        let mut state = self.state.borrow_mut();
+        if let Some(ball) = state.balls.last_mut() {
+            ball.lifetime = Some(Lifetime::new(lifetime_seconds));
+        }
+        Ok(())
+    }
+
+    /// Keeps spawning balls at (`x`, `y`), `rate` per second, shooting
+    /// upward at `speed` normalized canvas coordinates per second within a
+    /// cone of `spread` radians. The balls fade out after 2 seconds, and
+    /// spawns are dropped while all slots are in use. Any number of emitters
+    /// can run at once. Fails for a position or values that aren't finite,
+    /// and for rates above `MAX_EMIT_RATE`.
+    pub fn add_emitter(&mut self, x: f32, y: f32, rate: f32, spread: f32, speed: f32) -> Result<(), JsValue> {
+        let mut state = self.state.borrow_mut();
+        if let Err(message) = emitter::check_emitter(x, y, rate, spread, speed) {
+            state.warn(&message);
+            return Err(JsValue::from(&message));
+        }
+        let (x, y) = state.normalize_position(x, y);
+        state.emitters.push(Emitter::new(x, y, rate, spread, speed));
+        Ok(())
+    }
+
+    pub fn clear_emitters(&mut self) {
+        self.state.borrow_mut().emitters.clear();
+    }
+
+    /// Restarts the random numbers behind the emitters from `seed`, so runs
+    /// with the same seed and frames spawn the same balls.
+    pub fn set_seed(&mut self, seed: u32) {
+        let mut state = self.state.borrow_mut();
+        state.seed = seed;
+        state.rng = Rng::new(seed);
+    }
+
+    pub fn ball_count(&self) -> usize {
+        self.state.borrow().balls.len()
+    }
+
+    /// Chooses how positions passed to `add_metaball` and `trigger_ripple` are
+    /// interpreted. Radii are always a fraction of the shorter canvas side.
+    pub fn set_origin(&mut self, origin: Origin) {
+        self.state.borrow_mut().origin = origin;
+    }
+
+    /// Sets the velocity of the ball at `index`, in normalized canvas
+    /// coordinates per second. Fails unless both components are finite.
+    pub fn set_velocity(&mut self, index: usize, vx: f32, vy: f32) -> Result<(), JsValue> {
+        if !vx.is_finite() || !vy.is_finite() {
+            return Err(JsValue::from(&format!("Can't set a velocity of ({}, {}): it must be finite.", vx, vy)));
+        }
+        let mut state = self.state.borrow_mut();
+        let ball = state.balls.get_mut(index)
+            .ok_or_else(|| JsValue::from(&format!("Metaball {} doesn't exist.", index)))?;
+        ball.vx = vx;
+        ball.vy = vy;
+        Ok(())
+    }
+
+    /// Gives the ball at `index` a lifetime of `lifetime_seconds` like
+    /// `add_metaball_with_lifetime`, e.g. for a shape. Fails unless it's
+    /// finite and positive.
+    pub fn set_lifetime(&mut self, index: usize, lifetime_seconds: f32) -> Result<(), JsValue> {
+        if !lifetime_seconds.is_finite() || lifetime_seconds <= 0.0 {
+            return Err(JsValue::from(&format!("Can't set a lifetime of {} seconds: it must be positive.", lifetime_seconds)));
+        }
+        let mut state = self.state.borrow_mut();
+        let ball = state.balls.get_mut(index)
+            .ok_or_else(|| JsValue::from(&format!("Metaball {} doesn't exist.", index)))?;
+        ball.lifetime = Some(Lifetime::new(lifetime_seconds));
+        Ok(())
+    }
+
+    /// Sets the `params` uniform array, uploaded every frame. Entries not set
+    /// are 0; values beyond `MAX_PARAMS` are dropped with a warning.
+    pub fn set_params(&mut self, values: &[f32]) {
+        let mut state = self.state.borrow_mut();
+        if values.len() > MAX_PARAMS {
+            state.warn(&format!("Only {} params are supported, got {}.", MAX_PARAMS, values.len()));
+        }
+        state.params = values.iter().cloned().take(MAX_PARAMS).collect();
+    }
+
+    /// Shades the blobs in `levels` flat bands for a cartoon look. 0 or 1
+    /// goes back to smooth shading.
+    pub fn set_cel_shading(&mut self, levels: u32) {
+        self.state.borrow_mut().cel_levels = levels;
+    }
+
+    /// Keeps small balls from vanishing at low resolutions by rendering each
+    /// with a radius of at least `px` pixels of the target it's rendered
+    /// into, so after the resolution scale or pixel art. 0 disables it.
+    pub fn set_min_screen_radius(&mut self, px: f32) {
+        self.state.borrow_mut().min_screen_radius = px.max(0.0);
+    }
+
+    /// Renders the scene at `scale` times the region's resolution, from 0.1
+    /// to 1, and upscales it to the canvas.
+    pub fn set_resolution_scale(&mut self, scale: f32) {
+        self.state.borrow_mut().resolution_scale = scale.clamp(0.1, 1.0);
+    }
+
+    /// Renders the scene at `width` x `height` pixels and stretches it over
+    /// the rendered region with nearest filtering, for hard pixel edges. It
+    /// replaces the resolution scale and crisp edges. Passing 0 for either
+    /// goes back to them.
+    pub fn set_pixel_art(&mut self, width: u32, height: u32) {
+        self.state.borrow_mut().pixel_art = if width > 0 && height > 0 { Some((width, height)) } else { None };
+    }
+
+    /// The size of the target the scene is rendered into before upscaling,
+    /// or None when it's rendered at the canvas resolution.
+    pub fn render_size(&self) -> Option<Vec<i32>> {
+        let state = self.state.borrow();
+        let (width, height) = state.region_size;
+        upscale::render_size(width, height, state.resolution_scale, state.pixel_art)
+            .map(|(width, height)| vec![width, height])
+    }
+
+    /// While the resolution scale is below 1, renders only the field at the
+    /// reduced resolution and thresholds it at the full one, which keeps the
+    /// blob edges sharp.
+    pub fn set_crisp_edges_on_upscale(&mut self, crisp: bool) {
+        self.state.borrow_mut().crisp_edges = crisp;
+    }
+
+    /// While no balls are added, renders a few slowly drifting procedural
+    /// balls instead of the default mouse-driven spheres. They don't count
+    /// toward the ball limit.
+    pub fn set_ambient_default(&mut self, enabled: bool) {
+        self.state.borrow_mut().ambient_default = enabled;
+    }
+
+    /// Smooths the pointer position the effect follows. `Exponential` moves
+    /// `a` (0 to 1) of the way to the pointer each frame. `OneEuro` applies
+    /// the 1€ filter with a minimum cutoff of `a` Hz raised by `b` per unit
+    /// of speed (in canvas sizes per second), smoothing slow movements
+    /// strongly while following fast ones closely. `None` follows the pointer
+    /// as is, which is the default.
+    pub fn set_smoothing_algorithm(&mut self, kind: SmoothingKind, a: f32, b: f32) {
+        self.state.borrow_mut().pointer_filter = PointerFilter::new(Smoothing::from_kind(kind, a, b));
+    }
+
+    /// Grows the default mouse-driven spheres while the mouse moves fast and
+    /// shrinks them back as it comes to rest: their radius is scaled by
+    /// 1 + `factor` * the smoothed speed in canvas sizes per second, up to 3
+    /// times. 0 disables it.
+    pub fn set_mouse_speed_response(&mut self, factor: f32) {
+        self.state.borrow_mut().mouse_speed_response = factor;
+    }
+
+    /// Fades the blobs in from the background over the first `duration`
+    /// seconds of effect time. 0 shows them at full intensity right away.
+    pub fn set_fade_in(&mut self, duration: f32) {
+        self.state.borrow_mut().fade_in = duration.max(0.0);
+    }
+
+    /// Makes balls near the cursor spread out, growing their radius by up to
+    /// `strength` times at the cursor and fading out `radius` away from it,
+    /// a fraction of the shorter canvas side. A strength of 0 disables it.
+    pub fn set_melt_on_hover(&mut self, strength: f32, radius: f32) {
+        self.state.borrow_mut().melt = if strength != 0.0 && radius > 0.0 { Some((strength, radius)) } else { None };
+    }
+
+    /// Adds a tail of `segments` balls following the pointer, up to
+    /// `MAX_METABALLS`, each easing toward the one in front of it with a
+    /// time constant of `lag` seconds. 0 segments removes it. Tail balls are
+    /// drawn after the added ones and dropped first when there are too many.
+    pub fn set_pointer_tail(&mut self, segments: usize, lag: f32) {
+        let mut state = self.state.borrow_mut();
+        state.pointer_tail = if segments > 0 {
+            Some(PointerTail::new(segments, lag, state.pointer_position))
+        } else {
+            None
+        };
+    }
+
+    /// Sets the order the layers are drawn in, from bottom to top: "blobs"
+    /// and "paint". Unknown and repeated names are skipped with a warning,
+    /// and it fails with a warning, keeping the order, when "blobs" is
+    /// missing.
+    pub fn set_layer_order(&mut self, order: Vec<String>) -> Result<(), JsValue> {
+        let mut state = self.state.borrow_mut();
+        let (layer_order, warnings) = match layers::parse_layer_order(&order) {
+            Ok(parsed) => parsed,
+            Err(message) => {
+                state.warn(&message);
+                return Err(JsValue::from(&message));
+            },
+        };
+        for warning in warnings {
+            state.warn(&warning);
+        }
+        state.layer_order = layer_order;
+        Ok(())
+    }
+
+    /// Snaps the rendered balls, and the default mouse-driven spheres, to the
+    /// cell centers of a `cells_x` x `cells_y` grid over the rendered region
+    /// for a stepped movement. The simulation keeps the exact positions.
+    /// Passing 0 for either disables snapping.
+    pub fn set_grid_snap(&mut self, cells_x: u32, cells_y: u32) {
+        self.state.borrow_mut().grid_snap = if cells_x > 0 && cells_y > 0 { Some((cells_x, cells_y)) } else { None };
+    }
+
+    /// Colors the rim of the blobs, blending into the fill toward the
+    /// interior. Components are from 0 to 1.
+    pub fn set_edge_color(&mut self, r: f32, g: f32, b: f32) {
+        self.state.borrow_mut().edge_color = Some((r, g, b));
+    }
+
+    /// Colors the blobs and the background after a simulated day, easing
+    /// between night, dawn, day and dusk palettes reached at 0, 6, 12 and 18
+    /// o'clock. `hour` wraps around 24.
+    pub fn set_time_of_day(&mut self, hour: f32) {
+        self.state.borrow_mut().time_of_day = Some(hour.rem_euclid(24.0));
+    }
+
+    /// Merges the balls by a polynomial smooth minimum of their distances
+    /// instead of adding up their fields. `k`, in scene units (4 span the
+    /// shorter canvas side), is how far apart two balls start to flow into
+    /// each other, so higher is gooier. 0 adds up the fields again.
+    pub fn set_merge_smoothness(&mut self, k: f32) {
+        self.state.borrow_mut().merge_smoothness = k.max(0.0);
+    }
+
+    /// Inverts the field, so the blobs become holes in a solid filling the
+    /// rest of the canvas.
+    pub fn set_invert_field(&mut self, inverted: bool) {
+        self.state.borrow_mut().invert_field = inverted;
+    }
+
+    /// Culls the faces of the scene's quad `mode` selects. Its triangles are
+    /// wound counter-clockwise, so `Front` draws nothing. Defaults to `None`.
+    pub fn set_cull_face(&mut self, mode: CullMode) {
+        self.state.borrow_mut().cull_mode = mode;
+    }
+
+    /// Gamma corrects the output, raising the colors to `1 / gamma`: 2.2
+    /// treats the shading as linear and encodes it for an sRGB display. The
+    /// default of 1 outputs the shading as is.
+    pub fn set_gamma(&mut self, gamma: f32) {
+        self.state.borrow_mut().gamma = gamma.max(0.01);
+    }
+
+    /// Spawns a ball of `radius` where the canvas is clicked, and every frame
+    /// while the primary button is dragged. 0 turns it off.
+    pub fn set_spawn_on_click(&mut self, radius: f32) {
+        self.state.borrow_mut().spawn_radius = radius.max(0.0);
+    }
+
+    /// Ignores clicks and drags within `seconds` of the last spawned ball, so
+    /// they can't use up the slots all at once. 0 spawns on every one.
+    pub fn set_spawn_cooldown(&mut self, seconds: f32) {
+        self.state.borrow_mut().spawn_cooldown = seconds.max(0.0);
+    }
+
+    /// Makes dragging with the primary button paint into a buffer that's
+    /// kept across frames and added to the rendered field. The paint is
+    /// dropped when the mode is turned off or the canvas is resized.
+    pub fn set_paint_mode(&mut self, enabled: bool) {
+        let mut state = self.state.borrow_mut();
+        state.paint.enabled = enabled;
+        state.paint.release();
+    }
+
+    pub fn clear_paint(&mut self) {
+        self.state.borrow_mut().paint.clear_requested = true;
+    }
+
+    /// Captures the GL state before every frame and restores it afterwards,
+    /// for hosts that draw into the same context between frames. Off by
+    /// default, since it costs a round of getParameter calls per frame.
+    pub fn set_preserve_gl_state(&mut self, preserve: bool) {
+        self.state.borrow_mut().preserve_gl_state = preserve;
+    }
+
+    /// Measures the effect time from `origin_seconds`, in the wall clock of
+    /// `current_time`. Passing the same origin to several effects keeps
+    /// their animations in phase.
+    pub fn set_shared_clock(&mut self, origin_seconds: f64) {
+        self.state.borrow_mut().clock.sync_to_origin(origin_seconds);
+    }
+
+    /// Enables the keyboard (Space pauses the physics) and the wheel (which
+    /// changes the speed) on the canvas, applying only while it has focus,
+    /// and makes it focusable with a tabindex if it has none. Hover and
+    /// pointer input apply either way. Off by default, which leaves the
+    /// keyboard and wheel to the page.
+    pub fn set_require_focus(&mut self, required: bool) {
+        {
+            let mut state = self.state.borrow_mut();
+            state.require_focus = required;
+            state.sync_tabindex();
+        }
+        self.listen_to_focused_input();
+    }
+
+    /// Freezes the ball positions and velocities while the effect time keeps
+    /// advancing.
+    pub fn pause_physics(&mut self, paused: bool) {
+        self.state.borrow_mut().physics_paused = paused;
+    }
+
+    pub fn set_clear_strategy(&mut self, strategy: ClearStrategy) {
+        self.state.borrow_mut().clear_strategy = strategy;
+    }
+
+    pub fn stats(&self) -> RenderStats {
+        self.state.borrow().frame_stats.render_stats()
+    }
+
+    /// Registers a function called with the effect time in seconds at the
+    /// start of each frame. The parameters of an object it returns are
+    /// applied before the frame is drawn, as by their setters: `speed`,
+    /// `gamma`, `blur`, `merge_smoothness`, `time_of_day`,
+    /// `mouse_speed_response` and `invert_field`. Exceptions it throws are
+    /// reported to the warning callback and the frame is drawn as it was.
+    pub fn on_update(&mut self, on_update: Option<js_sys::Function>) {
+        self.state.borrow_mut().on_update = on_update;
+    }
+
+    /// Calls the `on_update` function and applies what it returns. The state
+    /// isn't borrowed during the call, so the function may use the effect.
+    pub(crate) fn run_on_update(&mut self) {
+        let Some(on_update) = self.state.borrow().on_update.clone() else {
+            return;
+        };
+        let time = self.state.borrow().clock.elapsed();
+        match on_update.call1(&JsValue::NULL, &JsValue::from(time)) {
+            Ok(values) => self.apply_frame_values(&values),
+            Err(error) => {
+                let message = error.as_string()
+                    .or_else(|| error.dyn_into::<js_sys::Error>().ok().map(|error| error.message().into()))
+                    .unwrap_or_else(|| "unknown error".to_string());
+                self.state.borrow().warn(&format!("on_update threw: {}", message));
+            },
+        }
+    }
+
+    fn apply_frame_values(&mut self, values: &JsValue) {
+        if !values.is_object() {
+            return;
+        }
+        let get = |key: &str| js_sys::Reflect::get(values, &JsValue::from(key)).unwrap_or(JsValue::UNDEFINED);
+        let number = |key: &str| get(key).as_f64().map(|value| value as f32);
+        if let Some(speed) = number("speed") {
+            self.set_speed(speed);
+        }
+        if let Some(gamma) = number("gamma") {
+            self.set_gamma(gamma);
+        }
+        if let Some(radius) = number("blur") {
+            self.set_blur(radius);
+        }
+        if let Some(k) = number("merge_smoothness") {
+            self.set_merge_smoothness(k);
+        }
+        if let Some(hour) = number("time_of_day") {
+            self.set_time_of_day(hour);
+        }
+        if let Some(factor) = number("mouse_speed_response") {
+            self.set_mouse_speed_response(factor);
+        }
+        if let Some(inverted) = get("invert_field").as_bool() {
+            self.set_invert_field(inverted);
+        }
+    }
+
+    /// Registers a function called with the frame time in milliseconds
+    /// whenever frames were dropped.
+    pub fn set_on_jank(&mut self, on_jank: Option<js_sys::Function>) {
+        self.state.borrow_mut().on_jank = on_jank;
+    }
+
+    /// Renders the canvas as a `tiles_x` x `tiles_y` grid of tiles, each only
+    /// evaluating the balls that reach into it, which allows up to
+    /// `MAX_TILED_METABALLS` balls. Passing 0 for either goes back to a single
+    /// pass, dropping the balls beyond `MAX_METABALLS`. Either side is
+    /// clamped to `MAX_TILES_PER_SIDE`.
+    pub fn set_tile_grid(&mut self, tiles_x: u32, tiles_y: u32) {
+        let mut state = self.state.borrow_mut();
+        state.tile_grid = if tiles_x > 0 && tiles_y > 0 {
+            Some((tiles_x.min(MAX_TILES_PER_SIDE), tiles_y.min(MAX_TILES_PER_SIDE)))
+        } else {
+            None
+        };
+        let capacity = state.ball_capacity();
+        if state.balls.len() > capacity {
+            state.warn(&format!("Dropping {} metaball(s) beyond {}.", state.balls.len() - capacity, capacity));
+            state.balls.truncate(capacity);
+        }
+    }
+
+    /// Splits the physics integration into sub-steps of at most `seconds`, so
+    /// long frames don't let balls tunnel through walls. At most
+    /// `MAX_SUBSTEPS` run per frame. 0 integrates each frame in one step.
+    /// Non-finite values are ignored.
+    pub fn set_max_physics_step(&mut self, seconds: f32) {
+        let mut state = self.state.borrow_mut();
+        if state.accepts_finite("set_max_physics_step", &[seconds]) {
+            state.max_physics_step = seconds.max(0.0);
+        }
+    }
+
+    /// Makes the balls bounce off each other instead of overlapping, keeping
+    /// `restitution` of their approach speed: 1 is perfectly elastic and 0
+    /// stops them on contact.
+    pub fn set_ball_collisions(&mut self, enabled: bool, restitution: f32) {
+        self.state.borrow_mut().ball_collisions = enabled.then(|| restitution.clamp(0.0, 1.0));
+    }
+
+    /// Renders a known pattern on a small offscreen canvas and checks a few
+    /// of its pixels, to find drivers that break the shaders before relying
+    /// on the effect, e.g. to fall back to a CSS-only one. The error names
+    /// the check that failed.
+    pub fn self_test(&self) -> Result<(), JsValue> {
+        crate::run_self_test(self.state.borrow().config.context_ids())
+    }
+
+    /// Copies every rendered frame to the canvases with `canvas_ids`, scaled
+    /// to their sizes, replacing any earlier mirrors. The mirrors show the
+    /// same frame as the effect's canvas, so they stay in sync. Fails if any
+    /// of the canvases doesn't exist.
+    pub fn mirror_to(&mut self, canvas_ids: Vec<String>) -> Result<(), JsValue> {
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or(JsValue::from("no `document` exists"))?;
+        for id in &canvas_ids {
+            document.get_element_by_id(id)
+                .and_then(|element| element.dyn_into::<web_sys::HtmlCanvasElement>().ok())
+                .ok_or(JsValue::from(&format!("Canvas {} doesn't exist.", id)))?;
+        }
+        self.state.borrow_mut().mirror_ids = canvas_ids;
+        Ok(())
+    }
+
+    /// Registers a function called with a message whenever a request is
+    /// rejected or degraded.
+    pub fn set_on_warning(&mut self, on_warning: Option<js_sys::Function>) {
+        self.state.borrow_mut().on_warning = on_warning;
+    }
+
+    /// Drives the ball radii from `analyser`'s frequency data, averaged into
+    /// `bands` bands.
+    pub fn connect_analyser(&mut self, analyser: web_sys::AnalyserNode, bands: usize) {
+        self.state.borrow_mut().analyser = Some(Analyser::new(analyser, bands));
+    }
+
+    /// Multiplies the rate the effect time advances at. Non-finite values are
+    /// ignored.
+    pub fn set_speed(&mut self, speed: f32) {
+        let mut state = self.state.borrow_mut();
+        if !state.accepts_finite("set_speed", &[speed]) {
+            return;
+        }
+        state.speed = speed;
+        state.speed_tween = None;
+    }
+
+    /// Eases the speed multiplier to `target` over `duration` seconds. Calling
+    /// it again mid-transition starts from the current speed. Non-finite
+    /// values are ignored.
+    pub fn animate_speed(&mut self, target: f32, duration: f32) {
+        let mut state = self.state.borrow_mut();
+        if !state.accepts_finite("animate_speed", &[target, duration]) {
+            return;
+        }
+        let from = state.speed;
+        state.speed_tween = Some(SpeedTween::new(from, target, duration));
+    }
+
+    /// Eases the animation to a stop while the cursor is over the canvas.
+    pub fn set_freeze_on_hover(&mut self, enabled: bool) {
+        self.state.borrow_mut().hover_freeze.enabled = enabled;
+    }
+
+    /// Flashes the whole canvas toward the accent color (`r`, `g`, `b`), from
+    /// 0 to 1, easing back over `duration` seconds, e.g. on a click or a
+    /// beat. Overlapping pulses add up.
+    pub fn trigger_color_pulse(&mut self, r: f32, g: f32, b: f32, duration: f32) {
+        let mut state = self.state.borrow_mut();
+        let start = state.clock.now();
+        state.pulses.push(ColorPulse { color: (r, g, b), start, duration });
+    }
+
+    /// Starts a ripple at a position interpreted according to `set_origin`,
+    /// e.g. from a click handler. Once `MAX_RIPPLES` are running, the oldest
+    /// one is replaced.
+    pub fn trigger_ripple(&mut self, x: f32, y: f32) {
+        let mut state = self.state.borrow_mut();
+        let now = state.clock.elapsed();
+        let (x, y) = state.normalize_position(x, y);
+        state.ripples.trigger(x, y, now);
+    }
+
+    /// `wavelength` is a fraction of the shorter canvas side, `speed` is in
+    /// shorter canvas sides per second and `decay` in 1/seconds. They're
+    /// ignored unless all are finite and the wavelength is above 0.
+    pub fn set_ripple_params(&mut self, wavelength: f32, speed: f32, decay: f32) {
+        let mut state = self.state.borrow_mut();
+        if !state.accepts_finite("set_ripple_params", &[wavelength, speed, decay]) {
+            return;
+        }
+        if wavelength <= 0.0 {
+            state.warn(&format!("Ignoring a ripple wavelength of {}: it must be above 0.", wavelength));
+            return;
+        }
+        state.ripples.params = RippleParams { wavelength, speed, decay };
+    }
+
+    /// Blurs the output with a separable Gaussian of `radius` drawing buffer
+    /// pixels, up to `MAX_BLUR_TAPS`. Below 1 disables the blur. Non-finite
+    /// values are ignored.
+    pub fn set_blur(&mut self, radius: f32) {
+        let mut state = self.state.borrow_mut();
+        if state.accepts_finite("set_blur", &[radius]) {
+            state.blur_radius = radius;
+        }
+    }
+
+    /// The field evaluated on the CPU at the centers of a `width` x `height`
+    /// grid over the rendered region, in grayscale with the inside of the
+    /// blobs white. It costs O(width * height * balls), so keep the grid small
+    /// when calling it every frame. The default mouse-driven spheres aren't
+    /// included.
+    pub fn field_to_image_data(&self, width: u32, height: u32) -> Result<web_sys::ImageData, JsValue> {
+        let data = self.field_computer().to_grayscale(width, height);
+        web_sys::ImageData::new_with_u8_clamped_array_and_sh(wasm_bindgen::Clamped(&data), width, height)
+    }
+
+    /// A box the blobs stay within, as [min x, min y, max x, max y] in
+    /// normalized canvas coordinates, bounded from the balls of the next
+    /// frame and their radii rather than sampled, so it may be a little
+    /// larger than the visible blobs. Empty without any balls. The default
+    /// mouse-driven spheres aren't included.
+    pub fn blob_bounding_box(&self) -> Vec<f32> {
+        let mut state = self.state.borrow_mut();
+        let balls = state.frame_balls();
+        let (width, height) = state.region_size;
+        field::bounding_box(&balls, width, height, state.merge_smoothness)
+            .map_or(vec![], |(min_x, min_y, max_x, max_y)| vec![min_x, min_y, max_x, max_y])
+    }
+
+    /// Shades `field_to_image_data` logarithmically, spanning 4 decades
+    /// below the surface, to show how the field falls off far from the
+    /// balls. The surface itself is unaffected.
+    pub fn set_field_log_scale(&mut self, log_scale: bool) {
+        self.state.borrow_mut().field_log_scale = log_scale;
+    }
+
+    /// SVG path data for the outlines where the CPU field crosses
+    /// `threshold` (1 is the rendered surface), traced by marching squares
+    /// on a `resolution` x `resolution` grid over the rendered region and
+    /// scaled to its size in pixels. Each blob becomes a closed subpath. The
+    /// default mouse-driven spheres aren't included.
+    pub fn field_isolines_svg(&self, threshold: f32, resolution: u32) -> String {
+        let contours = isolines::trace_contours(&self.field_computer(), threshold, resolution);
+        let (width, height) = self.state.borrow().region_size;
+        isolines::to_svg_path(&contours, width, height)
+    }
+
+    /// Insets the rendered area by `px` drawing buffer pixels on every side,
+    /// leaving the border in the clear color.
+    pub fn set_padding(&mut self, px: f32) {
+        let mut state = self.state.borrow_mut();
+        state.padding = px;
+        state.sync_region_size();
+    }
+
+    /// Confines every clear and draw to the rectangle at (`x`, `y`) from the
+    /// top-left corner, `w` x `h` drawing buffer pixels large, leaving the
+    /// rest of the canvas untouched for other content. Unlike padding it also
+    /// keeps the clear out.
+    pub fn set_scissor_rect(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        self.state.borrow_mut().scissor_rect = Some(Region::new(x, y, w.max(0.0), h.max(0.0)));
+    }
+
+    pub fn clear_scissor_rect(&mut self) {
+        self.state.borrow_mut().scissor_rect = None;
+    }
+
+    /// Keeps the effect at an aspect ratio of `ratio` (width / height)
+    /// whatever the canvas shape, centering it and filling the bars left over
+    /// with the background. 0 fills the canvas.
+    pub fn set_target_aspect(&mut self, ratio: f32) {
+        let mut state = self.state.borrow_mut();
+        state.target_aspect = ratio.max(0.0);
+        state.sync_region_size();
+    }
+}
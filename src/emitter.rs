@@ -0,0 +1,95 @@
+use crate::metaball::{self, Lifetime, Metaball, Shape};
+
+/// Seed the random number generator starts from until `set_seed` is called.
+pub const DEFAULT_SEED: u32 = 0x9e37_79b9;
+
+/// Radius of the emitted balls, as a fraction of the shorter canvas side.
+pub const EMITTED_RADIUS: f32 = 0.04;
+
+/// Seconds an emitted ball lives before it's removed.
+pub const EMITTED_LIFETIME: f32 = 2.0;
+
+/// Balls per second an emitter may spawn.
+pub const MAX_EMIT_RATE: f32 = 1000.0;
+
+/// Checks that an emitter at (`x`, `y`) spawns balls that can be evaluated,
+/// at a finite `rate` of at most `MAX_EMIT_RATE`, with a finite `spread` and
+/// `speed`.
+pub fn check_emitter(x: f32, y: f32, rate: f32, spread: f32, speed: f32) -> Result<(), String> {
+    if !rate.is_finite() || rate > MAX_EMIT_RATE {
+        return Err(format!("Can't emit {} balls per second: at most {} are allowed.", rate, MAX_EMIT_RATE));
+    }
+    if !spread.is_finite() || !speed.is_finite() {
+        return Err(format!("Can't emit with a spread of {} and a speed of {}: they must be finite.", spread, speed));
+    }
+    metaball::check_ball(x, y, EMITTED_RADIUS, Shape::Circle)
+}
+
+/// A small deterministic random number generator (xorshift32), so seeded
+/// effects play out the same every time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rng {
+    state: u32,
+}
+
+impl Rng {
+    pub fn new(seed: u32) -> Rng {
+        // Zero is a fixed point of xorshift.
+        Rng { state: if seed == 0 { DEFAULT_SEED } else { seed } }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        x
+    }
+
+    /// A uniformly distributed value in [0, 1).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+/// Spawns balls from a point at a steady rate, shooting them upward within a
+/// cone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Emitter {
+    pub x: f32,
+    pub y: f32,
+    /// Balls per second.
+    pub rate: f32,
+    /// Full angle of the cone in radians, centered on straight up.
+    pub spread: f32,
+    /// In normalized canvas coordinates per second.
+    pub speed: f32,
+    /// Fraction of the next ball accumulated so far.
+    pending: f32,
+}
+
+impl Emitter {
+    pub fn new(x: f32, y: f32, rate: f32, spread: f32, speed: f32) -> Emitter {
+        Emitter { x, y, rate: rate.max(0.0), spread, speed, pending: 0.0 }
+    }
+
+    /// The balls due after `dt` more seconds, with velocities drawn from
+    /// `rng`. At most `free` are returned; the others are dropped.
+    pub fn emit(&mut self, dt: f32, free: usize, rng: &mut Rng) -> Vec<Metaball> {
+        self.pending += self.rate * dt;
+        let count = self.pending.floor();
+        self.pending -= count;
+        (0..(count as usize).min(free))
+            .map(|_| {
+                let angle = (rng.next_f32() - 0.5) * self.spread;
+                let mut ball = Metaball::new(self.x, self.y, EMITTED_RADIUS);
+                ball.vx = angle.sin() * self.speed;
+                ball.vy = -angle.cos() * self.speed;
+                ball.lifetime = Some(Lifetime::new(EMITTED_LIFETIME));
+                ball.emitted = true;
+                ball
+            })
+            .collect()
+    }
+}
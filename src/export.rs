@@ -0,0 +1,205 @@
+use crate::config::Config;
+use crate::effect::State;
+use crate::metaball::Shape;
+use crate::smoothing::Smoothing;
+
+/// Emits a JS snippet that reproduces `state` with the wasm-bindgen API.
+/// Settings at their defaults are left out. A connected analyser can't be
+/// serialized and isn't included.
+pub(crate) fn export_as_code(state: &State) -> String {
+    let default_config = Config::default();
+    let defaults = State::new(0.0, default_config.clone());
+    let config = &state.config;
+    let mut lines = vec!["const config = new Config();".to_string()];
+
+    if config.pointer_target != default_config.pointer_target {
+        lines.push(format!("config.pointer_target = PointerTarget.{:?};", config.pointer_target));
+    }
+    if config.dithering != default_config.dithering {
+        lines.push(format!("config.dithering = {};", config.dithering));
+    }
+    if config.context_ids() != default_config.context_ids() {
+        let ids: Vec<String> = config.context_ids().iter().map(|id| format!("{:?}", id)).collect();
+        lines.push(format!("config.set_context_ids([{}]);", ids.join(", ")));
+    }
+
+    if config.coalesce_pointer_events != default_config.coalesce_pointer_events {
+        lines.push(format!("config.coalesce_pointer_events = {};", config.coalesce_pointer_events));
+    }
+
+    if config.capture_pointer != default_config.capture_pointer {
+        lines.push(format!("config.capture_pointer = {};", config.capture_pointer));
+    }
+
+    lines.push("const effect = start_with_config(config);".to_string());
+
+    // A running transition is exported at its target.
+    let speed = state.speed_tween.as_ref().map_or(state.speed, |tween| tween.target());
+    if speed != defaults.speed {
+        lines.push(format!("effect.set_speed({});", speed));
+    }
+    if state.hover_freeze.enabled != defaults.hover_freeze.enabled {
+        lines.push(format!("effect.set_freeze_on_hover({});", state.hover_freeze.enabled));
+    }
+    if state.padding != defaults.padding {
+        lines.push(format!("effect.set_padding({});", state.padding));
+    }
+    if let Some(rect) = state.scissor_rect {
+        lines.push(format!("effect.set_scissor_rect({}, {}, {}, {});", rect.x, rect.y, rect.width, rect.height));
+    }
+    if state.target_aspect != defaults.target_aspect {
+        lines.push(format!("effect.set_target_aspect({});", state.target_aspect));
+    }
+    if state.ripples.params != defaults.ripples.params {
+        let params = state.ripples.params;
+        lines.push(format!("effect.set_ripple_params({}, {}, {});", params.wavelength, params.speed, params.decay));
+    }
+    if state.origin != defaults.origin {
+        lines.push(format!("effect.set_origin(Origin.{:?});", state.origin));
+    }
+    if let Some((tiles_x, tiles_y)) = state.tile_grid {
+        lines.push(format!("effect.set_tile_grid({}, {});", tiles_x, tiles_y));
+    }
+    if state.clear_strategy != defaults.clear_strategy {
+        lines.push(format!("effect.set_clear_strategy({});", state.clear_strategy.to_code()));
+    }
+    if state.blur_radius != defaults.blur_radius {
+        lines.push(format!("effect.set_blur({});", state.blur_radius));
+    }
+    if let Some((width, height)) = state.pixel_art {
+        lines.push(format!("effect.set_pixel_art({}, {});", width, height));
+    }
+    if state.resolution_scale != defaults.resolution_scale {
+        lines.push(format!("effect.set_resolution_scale({});", state.resolution_scale));
+    }
+    if state.crisp_edges != defaults.crisp_edges {
+        lines.push(format!("effect.set_crisp_edges_on_upscale({});", state.crisp_edges));
+    }
+    if state.ambient_default != defaults.ambient_default {
+        lines.push(format!("effect.set_ambient_default({});", state.ambient_default));
+    }
+    if state.mouse_speed_response != defaults.mouse_speed_response {
+        lines.push(format!("effect.set_mouse_speed_response({});", state.mouse_speed_response));
+    }
+    if let Some((strength, radius)) = state.melt {
+        lines.push(format!("effect.set_melt_on_hover({}, {});", strength, radius));
+    }
+    if let Some(tail) = &state.pointer_tail {
+        lines.push(format!("effect.set_pointer_tail({}, {});", tail.positions().len(), tail.lag()));
+    }
+    if state.layer_order != defaults.layer_order {
+        let names: Vec<String> = state.layer_order.iter().map(|layer| format!("\"{}\"", layer.name())).collect();
+        lines.push(format!("effect.set_layer_order([{}]);", names.join(", ")));
+    }
+    if let Some((cells_x, cells_y)) = state.grid_snap {
+        lines.push(format!("effect.set_grid_snap({}, {});", cells_x, cells_y));
+    }
+    if let Some(hour) = state.time_of_day {
+        lines.push(format!("effect.set_time_of_day({});", hour));
+    }
+    if let Some((r, g, b)) = state.edge_color {
+        lines.push(format!("effect.set_edge_color({}, {}, {});", r, g, b));
+    }
+    if state.fade_in != defaults.fade_in {
+        lines.push(format!("effect.set_fade_in({});", state.fade_in));
+    }
+    if state.merge_smoothness != defaults.merge_smoothness {
+        lines.push(format!("effect.set_merge_smoothness({});", state.merge_smoothness));
+    }
+    if state.invert_field != defaults.invert_field {
+        lines.push(format!("effect.set_invert_field({});", state.invert_field));
+    }
+    if state.cull_mode != defaults.cull_mode {
+        lines.push(format!("effect.set_cull_face(CullMode.{:?});", state.cull_mode));
+    }
+    if state.field_log_scale != defaults.field_log_scale {
+        lines.push(format!("effect.set_field_log_scale({});", state.field_log_scale));
+    }
+    if state.gamma != defaults.gamma {
+        lines.push(format!("effect.set_gamma({});", state.gamma));
+    }
+    if state.paint.enabled != defaults.paint.enabled {
+        lines.push(format!("effect.set_paint_mode({});", state.paint.enabled));
+    }
+    if state.min_screen_radius != defaults.min_screen_radius {
+        lines.push(format!("effect.set_min_screen_radius({});", state.min_screen_radius));
+    }
+    if state.preserve_gl_state != defaults.preserve_gl_state {
+        lines.push(format!("effect.set_preserve_gl_state({});", state.preserve_gl_state));
+    }
+    if state.cel_levels != defaults.cel_levels {
+        lines.push(format!("effect.set_cel_shading({});", state.cel_levels));
+    }
+    if !state.params.is_empty() {
+        let values: Vec<String> = state.params.iter().map(|value| value.to_string()).collect();
+        lines.push(format!("effect.set_params(new Float32Array([{}]));", values.join(", ")));
+    }
+    if state.require_focus != defaults.require_focus {
+        lines.push(format!("effect.set_require_focus({});", state.require_focus));
+    }
+    if state.physics_paused != defaults.physics_paused {
+        lines.push(format!("effect.pause_physics({});", state.physics_paused));
+    }
+    if state.max_physics_step != defaults.max_physics_step {
+        lines.push(format!("effect.set_max_physics_step({});", state.max_physics_step));
+    }
+    if let Some(restitution) = state.ball_collisions {
+        lines.push(format!("effect.set_ball_collisions(true, {});", restitution));
+    }
+    if state.spawn_radius != defaults.spawn_radius {
+        lines.push(format!("effect.set_spawn_on_click({});", state.spawn_radius));
+    }
+    if state.spawn_cooldown != defaults.spawn_cooldown {
+        lines.push(format!("effect.set_spawn_cooldown({});", state.spawn_cooldown));
+    }
+    if !state.mirror_ids.is_empty() {
+        let ids: Vec<String> = state.mirror_ids.iter().map(|id| format!("{:?}", id)).collect();
+        lines.push(format!("effect.mirror_to([{}]);", ids.join(", ")));
+    }
+    match state.pointer_filter.smoothing {
+        Smoothing::None => {},
+        Smoothing::Exponential { alpha } => lines.push(format!(
+            "effect.set_smoothing_algorithm(SmoothingKind.Exponential, {}, 0);", alpha
+        )),
+        Smoothing::OneEuro { min_cutoff, beta } => lines.push(format!(
+            "effect.set_smoothing_algorithm(SmoothingKind.OneEuro, {}, {});", min_cutoff, beta
+        )),
+    }
+    if state.seed != defaults.seed {
+        lines.push(format!("effect.set_seed({});", state.seed));
+    }
+    for emitter in &state.emitters {
+        let (x, y) = state.denormalize_position(emitter.x, emitter.y);
+        lines.push(format!(
+            "effect.add_emitter({}, {}, {}, {}, {});",
+            x, y, emitter.rate, emitter.spread, emitter.speed
+        ));
+    }
+    // Emitted balls are left to the emitters exported above.
+    for (i, ball) in state.balls.iter().filter(|ball| !ball.emitted).enumerate() {
+        let (x, y) = state.denormalize_position(ball.x, ball.y);
+        match ball.shape {
+            // The remaining lifetime restarts as a whole one.
+            Shape::Circle if ball.lifetime.is_some() => lines.push(format!(
+                "effect.add_metaball_with_lifetime({}, {}, {}, {});",
+                x, y, ball.radius, ball.lifetime.unwrap().remaining
+            )),
+            Shape::Circle => lines.push(format!("effect.add_metaball({}, {}, {});", x, y, ball.radius)),
+            shape => {
+                let (a, b) = shape.params();
+                lines.push(format!(
+                    "effect.add_metaball_shape({}, {}, {}, ShapeKind.{:?}, {}, {});",
+                    x, y, ball.radius, shape.kind(), a, b
+                ));
+                if let Some(lifetime) = ball.lifetime {
+                    lines.push(format!("effect.set_lifetime({}, {});", i, lifetime.remaining));
+                }
+            },
+        }
+        if ball.vx != 0.0 || ball.vy != 0.0 {
+            lines.push(format!("effect.set_velocity({}, {}, {});", i, ball.vx, ball.vy));
+        }
+    }
+
+    lines.join("\n")
+}
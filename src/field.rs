@@ -0,0 +1,205 @@
+use crate::metaball::{Metaball, Shape};
+
+/// `smoothness` of the fragment shader's smooth minimum.
+pub const SMOOTHNESS: f32 = 2.0;
+
+/// `sceneUnit` of the fragment shader: scene units spanned by the shorter
+/// canvas side on the z = 0 plane.
+pub const SCENE_UNIT: f32 = 4.0;
+
+/// Field value at and above which a point is inside a blob.
+pub const THRESHOLD: f32 = 1.0;
+
+/// Decades of the field below `THRESHOLD` the logarithmic scale spans
+/// before reaching black.
+pub const LOG_SCALE_DECADES: f32 = 4.0;
+
+/// The gray level in [0, 1] the field images show `value` at: linear up to
+/// `THRESHOLD`, or with `log_scale` logarithmic over `LOG_SCALE_DECADES`
+/// below it, so faint contributions far from the balls stay visible.
+pub fn field_intensity(value: f32, log_scale: bool) -> f32 {
+    let value = value / THRESHOLD;
+    if log_scale {
+        if value <= 0.0 {
+            return 0.0;
+        }
+        return (1.0 + value.log10() / LOG_SCALE_DECADES).clamp(0.0, 1.0);
+    }
+    value.min(1.0)
+}
+
+/// Evaluates the field the fragment shader ray marches on the z = 0 plane.
+/// The value is the sum of `exp(-SMOOTHNESS * (distance - radius))` over the
+/// balls, which reaches `THRESHOLD` exactly on the blob surfaces. With a merge
+/// smoothness the distances are combined by `smooth_min` instead, and the
+/// value is `exp(-SMOOTHNESS * distance)` of the result.
+pub struct FieldComputer {
+    /// Ball centers and radii in scene units, with their shapes.
+    balls: Vec<(f32, f32, f32, Shape)>,
+    width: f32,
+    height: f32,
+    merge_smoothness: f32,
+    inverted: bool,
+    log_scale: bool,
+}
+
+impl FieldComputer {
+    /// A field for `balls` on a `width` x `height` region.
+    pub fn new(balls: &[Metaball], width: f32, height: f32) -> FieldComputer {
+        let mut field = FieldComputer { balls: vec![], width, height, merge_smoothness: 0.0, inverted: false, log_scale: false };
+        field.balls = balls.iter()
+            .map(|ball| {
+                let (x, y) = field.to_scene(ball.x, ball.y);
+                (x, y, ball.radius * SCENE_UNIT, ball.shape)
+            })
+            .collect();
+        field
+    }
+
+    /// Combines the balls with `smooth_min` of parameter `k`, in scene units,
+    /// like the fragment shader does after `set_merge_smoothness`.
+    pub fn with_merge_smoothness(mut self, k: f32) -> FieldComputer {
+        self.merge_smoothness = k;
+        self
+    }
+
+    /// Swaps the inside and the outside of the blobs for `is_inside_blob`,
+    /// like the fragment shader does after `set_invert_field`.
+    pub fn with_inverted(mut self, inverted: bool) -> FieldComputer {
+        self.inverted = inverted;
+        self
+    }
+
+    /// Shades `to_grayscale` on a logarithmic scale, see `field_intensity`.
+    pub fn with_log_scale(mut self, log_scale: bool) -> FieldComputer {
+        self.log_scale = log_scale;
+        self
+    }
+
+    /// The `toScene` of the fragment shader.
+    fn to_scene(&self, x: f32, y: f32) -> (f32, f32) {
+        let shorter = self.width.min(self.height);
+        (
+            (x * 2.0 - 1.0) * self.width / shorter * 2.0,
+            (1.0 - y * 2.0) * self.height / shorter * 2.0,
+        )
+    }
+
+    /// The field value at normalized canvas coordinates.
+    pub fn field_at(&self, x: f32, y: f32) -> f32 {
+        if self.merge_smoothness > 0.0 {
+            return (-SMOOTHNESS * self.distance_at(x, y)).exp();
+        }
+        let (x, y) = self.to_scene(x, y);
+        self.balls.iter()
+            .map(|&(ball_x, ball_y, radius, shape)| {
+                let distance = shape_distance(x - ball_x, y - ball_y, radius, shape);
+                (-SMOOTHNESS * distance).exp()
+            })
+            .sum()
+    }
+
+    /// The signed distance to the blob surface the fragment shader derives
+    /// from the field, in scene units and negative inside.
+    pub fn distance_at(&self, x: f32, y: f32) -> f32 {
+        if self.merge_smoothness > 0.0 {
+            let (x, y) = self.to_scene(x, y);
+            return self.balls.iter()
+                .map(|&(ball_x, ball_y, radius, shape)| shape_distance(x - ball_x, y - ball_y, radius, shape))
+                .fold(NO_BALLS_DISTANCE, |distance, ball| smooth_min(distance, ball, self.merge_smoothness));
+        }
+        // Far from every ball the field underflows to 0.
+        -self.field_at(x, y).max(f32::MIN_POSITIVE).ln() / SMOOTHNESS
+    }
+
+    pub fn is_inside_blob(&self, x: f32, y: f32) -> bool {
+        (self.field_at(x, y) >= THRESHOLD) != self.inverted
+    }
+
+    /// RGBA pixels of the field sampled at the centers of a `width` x
+    /// `height` grid, in grayscale mapped by `field_intensity` with
+    /// `THRESHOLD` and above being white.
+    /// The cost is O(width * height * balls).
+    pub fn to_grayscale(&self, width: u32, height: u32) -> Vec<u8> {
+        let mut data = Vec::with_capacity((width * height * 4) as usize);
+        for row in 0..height {
+            for column in 0..width {
+                let x = (column as f32 + 0.5) / width as f32;
+                let y = (row as f32 + 0.5) / height as f32;
+                let value = field_intensity(self.field_at(x, y), self.log_scale);
+                let gray = (value * 255.0).round() as u8;
+                data.extend_from_slice(&[gray, gray, gray, 255]);
+            }
+        }
+        data
+    }
+}
+
+/// A box in normalized canvas coordinates, as (min x, min y, max x, max y),
+/// that the inside of the blobs around `balls` can't leave on a `width` x
+/// `height` region. Besides the balls' own extent it allows for the field of
+/// the others pushing the surface outward: with n balls a point is only
+/// inside if one of them contributes at least 1 / n, which bounds the growth
+/// to ln(n) / `SMOOTHNESS`. A merge smoothness of k instead lowers the
+/// distance by at most k / 4 per ball merged in. None without any balls.
+pub fn bounding_box(balls: &[Metaball], width: f32, height: f32, merge_smoothness: f32) -> Option<(f32, f32, f32, f32)> {
+    if balls.is_empty() {
+        return None;
+    }
+    let growth = if merge_smoothness > 0.0 {
+        merge_smoothness / 4.0 * (balls.len() - 1) as f32
+    } else {
+        (balls.len() as f32).ln() / SMOOTHNESS
+    } / SCENE_UNIT;
+    let shorter = width.min(height);
+    let (scale_x, scale_y) = (shorter / width, shorter / height);
+    Some(balls.iter().fold(
+        (f32::INFINITY, f32::INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        |(min_x, min_y, max_x, max_y), ball| {
+            let reach = ball.extent() + growth;
+            (
+                min_x.min(ball.x - reach * scale_x),
+                min_y.min(ball.y - reach * scale_y),
+                max_x.max(ball.x + reach * scale_x),
+                max_y.max(ball.y + reach * scale_y),
+            )
+        },
+    ))
+}
+
+/// Distance the fragment shader reports without any balls.
+const NO_BALLS_DISTANCE: f32 = 1000.0;
+
+/// Smallest radius, in scene units, the ellipse distance divides by, as in
+/// the fragment shader.
+const MIN_RADIUS: f32 = 1e-6;
+
+/// The polynomial smooth minimum of the fragment shader's `smoothMinPoly`:
+/// the minimum of `a` and `b`, rounded off where they are within `k` of each
+/// other so the blobs flow into each other over about that distance.
+pub fn smooth_min(a: f32, b: f32, k: f32) -> f32 {
+    let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+    b + (a - b) * h - k * h * (1.0 - h)
+}
+
+/// The `ballDistance` of the fragment shader on the z = 0 plane: the signed
+/// distance from a point at (`dx`, `dy`) off the center to the outline, in
+/// scene units.
+fn shape_distance(dx: f32, dy: f32, radius: f32, shape: Shape) -> f32 {
+    match shape {
+        Shape::Circle => (dx * dx + dy * dy).sqrt() - radius,
+        Shape::Ellipse { rx, ry } => {
+            // Balls built in Rust skip the checks of `add_metaball_shape`.
+            let (rx, ry) = ((rx * SCENE_UNIT).max(MIN_RADIUS), (ry * SCENE_UNIT).max(MIN_RADIUS));
+            let shorter = rx.min(ry);
+            (((dx / rx).powi(2) + (dy / ry).powi(2)).sqrt() - 1.0) * shorter
+        },
+        Shape::Capsule { length, angle } => {
+            let half_length = length * SCENE_UNIT / 2.0;
+            let (axis_x, axis_y) = (angle.cos() * half_length, angle.sin() * half_length);
+            let t = ((dx * axis_x + dy * axis_y) / (half_length * half_length).max(1e-6)).clamp(-1.0, 1.0);
+            let (dx, dy) = (dx - axis_x * t, dy - axis_y * t);
+            (dx * dx + dy * dy).sqrt() - radius
+        },
+    }
+}
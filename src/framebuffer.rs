@@ -0,0 +1,191 @@
+use wasm_bindgen::prelude::*;
+use web_sys::{WebGlBuffer, WebGlFramebuffer, WebGlRenderingContext, WebGlTexture};
+
+use crate::geometry::{QUAD_INDICES, QUAD_POSITIONS};
+
+/// An offscreen RGBA framebuffer with its color texture, both deleted when
+/// it's dropped.
+pub struct RenderTarget {
+    pub framebuffer: WebGlFramebuffer,
+    pub texture: WebGlTexture,
+    pub width: i32,
+    pub height: i32,
+    /// The minification and magnification filter of the texture.
+    filter: u32,
+    /// The context the texture and framebuffer belong to.
+    context: WebGlRenderingContext,
+}
+
+impl RenderTarget {
+    pub fn new(context: &WebGlRenderingContext, width: i32, height: i32) -> Result<RenderTarget, JsValue> {
+        let texture = context.create_texture().ok_or(JsValue::from("Couldn't create a texture."))?;
+        context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
+        // non power of two textures can't wrap or be mipmapped
+        for (name, value) in [
+            (WebGlRenderingContext::TEXTURE_WRAP_S, WebGlRenderingContext::CLAMP_TO_EDGE),
+            (WebGlRenderingContext::TEXTURE_WRAP_T, WebGlRenderingContext::CLAMP_TO_EDGE),
+            (WebGlRenderingContext::TEXTURE_MIN_FILTER, WebGlRenderingContext::LINEAR),
+            (WebGlRenderingContext::TEXTURE_MAG_FILTER, WebGlRenderingContext::LINEAR),
+        ].iter() {
+            context.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, *name, *value as i32);
+        }
+
+        let framebuffer = context.create_framebuffer().ok_or(JsValue::from("Couldn't create a framebuffer."))?;
+        let mut target = RenderTarget {
+            framebuffer,
+            texture,
+            width: 0,
+            height: 0,
+            filter: WebGlRenderingContext::LINEAR,
+            context: context.clone(),
+        };
+        target.resize(context, width, height)?;
+
+        context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&target.framebuffer));
+        context.framebuffer_texture_2d(
+            WebGlRenderingContext::FRAMEBUFFER,
+            WebGlRenderingContext::COLOR_ATTACHMENT0,
+            WebGlRenderingContext::TEXTURE_2D,
+            Some(&target.texture),
+            0
+        );
+        context.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+        context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+
+        Ok(target)
+    }
+
+    /// Reallocates the texture when the size changed, which clears it.
+    pub fn resize(&mut self, context: &WebGlRenderingContext, width: i32, height: i32) -> Result<(), JsValue> {
+        if width == self.width && height == self.height {
+            return Ok(());
+        }
+        context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&self.texture));
+        context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            WebGlRenderingContext::TEXTURE_2D,
+            0,
+            WebGlRenderingContext::RGBA as i32,
+            width,
+            height,
+            0,
+            WebGlRenderingContext::RGBA,
+            WebGlRenderingContext::UNSIGNED_BYTE,
+            None
+        )?;
+        self.width = width;
+        self.height = height;
+        Ok(())
+    }
+
+    /// Sets how the texture is sampled, LINEAR by default.
+    pub fn set_filter(&mut self, context: &WebGlRenderingContext, filter: u32) {
+        if filter == self.filter {
+            return;
+        }
+        context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&self.texture));
+        context.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, WebGlRenderingContext::TEXTURE_MIN_FILTER, filter as i32);
+        context.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, WebGlRenderingContext::TEXTURE_MAG_FILTER, filter as i32);
+        self.filter = filter;
+    }
+
+    pub fn bind(&self, context: &WebGlRenderingContext) {
+        context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&self.framebuffer));
+        context.viewport(0, 0, self.width, self.height);
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        self.context.delete_framebuffer(Some(&self.framebuffer));
+        self.context.delete_texture(Some(&self.texture));
+    }
+}
+
+/// The buffers of the quad every pass draws. They don't depend on the size
+/// of the canvas, so they're uploaded once.
+pub struct QuadBuffers {
+    pub position: WebGlBuffer,
+    pub index: WebGlBuffer,
+}
+
+impl QuadBuffers {
+    pub fn new(context: &WebGlRenderingContext) -> Result<QuadBuffers, JsValue> {
+        let position = context.create_buffer().ok_or(JsValue::from("Couldn't create a buffer."))?;
+        context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&position));
+        unsafe {
+            context.buffer_data_with_array_buffer_view(
+                WebGlRenderingContext::ARRAY_BUFFER,
+                &js_sys::Float32Array::view(&QUAD_POSITIONS),
+                WebGlRenderingContext::STATIC_DRAW
+            );
+        }
+
+        let index = context.create_buffer().ok_or(JsValue::from("Couldn't create a buffer."))?;
+        context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&index));
+        unsafe {
+            context.buffer_data_with_array_buffer_view(
+                WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+                &js_sys::Uint16Array::view(&QUAD_INDICES),
+                WebGlRenderingContext::STATIC_DRAW
+            );
+        }
+
+        Ok(QuadBuffers { position, index })
+    }
+}
+
+/// The GL objects the render loop keeps across frames. When the drawing
+/// buffer is resized only the targets of its size are reallocated; the quad
+/// is kept as it is.
+pub struct FrameResources {
+    pub quad: QuadBuffers,
+    /// The blurred scene and the horizontally blurred one, while blurring.
+    pub(crate) blur_targets: Option<(RenderTarget, RenderTarget)>,
+    width: i32,
+    height: i32,
+}
+
+impl FrameResources {
+    pub fn new(context: &WebGlRenderingContext, width: i32, height: i32) -> Result<FrameResources, JsValue> {
+        Ok(FrameResources { quad: QuadBuffers::new(context)?, blur_targets: None, width, height })
+    }
+
+    /// The size of the drawing buffer the targets are allocated for.
+    pub fn size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    pub fn is_blurring(&self) -> bool {
+        self.blur_targets.is_some()
+    }
+
+    /// The target the scene is drawn into to be blurred, while blurring.
+    pub fn blur_scene(&self) -> Option<&RenderTarget> {
+        self.blur_targets.as_ref().map(|(scene, _)| scene)
+    }
+
+    /// Allocates the blur targets at the current size if `enabled` and they
+    /// don't exist yet, or drops them.
+    pub fn set_blur(&mut self, context: &WebGlRenderingContext, enabled: bool) -> Result<(), JsValue> {
+        if !enabled {
+            self.blur_targets = None;
+        } else if self.blur_targets.is_none() {
+            let scene = RenderTarget::new(context, self.width, self.height)?;
+            let pass = RenderTarget::new(context, self.width, self.height)?;
+            self.blur_targets = Some((scene, pass));
+        }
+        Ok(())
+    }
+
+    /// Follows a drawing buffer of `width` x `height`, reallocating the
+    /// targets only if the size changed.
+    pub fn resize(&mut self, context: &WebGlRenderingContext, width: i32, height: i32) -> Result<(), JsValue> {
+        self.width = width;
+        self.height = height;
+        if let Some((scene, pass)) = &mut self.blur_targets {
+            scene.resize(context, width, height)?;
+            pass.resize(context, width, height)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use wasm_bindgen::prelude::*;
+
+/// Corners of the full-screen quad in clip space: top left, top right,
+/// bottom left and bottom right.
+pub const QUAD_POSITIONS: [f32; 12] = [
+    -1.0,  1.0, 0.0,
+     1.0,  1.0, 0.0,
+    -1.0, -1.0, 0.0,
+     1.0, -1.0, 0.0
+];
+
+/// The quad's two triangles, both wound counter-clockwise.
+pub const QUAD_INDICES: [u16; 6] = [
+    0, 2, 1,
+    1, 2, 3
+];
+
+/// Which faces are culled before rasterizing.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CullMode {
+    None,
+    Back,
+    Front,
+}
+
+/// Whether each triangle of `indices` into the xyz `positions` is wound
+/// counter-clockwise on screen, which WebGL treats as front facing.
+pub fn front_facing(positions: &[f32], indices: &[u16]) -> Vec<bool> {
+    let corner = |index: u16| {
+        let index = index as usize * 3;
+        (positions[index], positions[index + 1])
+    };
+    indices.chunks(3)
+        .map(|triangle| {
+            let (a, b, c) = (corner(triangle[0]), corner(triangle[1]), corner(triangle[2]));
+            (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0) > 0.0
+        })
+        .collect()
+}
@@ -0,0 +1,133 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{WebGlBuffer, WebGlFramebuffer, WebGlProgram, WebGlRenderingContext, WebGlTexture};
+
+/// The GL state a frame changes, captured so that it can be handed back to a
+/// host drawing into the same context.
+pub struct GlState {
+    blend: bool,
+    scissor_test: bool,
+    cull_face: bool,
+    cull_face_mode: u32,
+    front_face: u32,
+    blend_func: [u32; 4],
+    clear_color: Vec<f32>,
+    viewport: Vec<i32>,
+    scissor_box: Vec<i32>,
+    program: Option<WebGlProgram>,
+    array_buffer: Option<WebGlBuffer>,
+    element_array_buffer: Option<WebGlBuffer>,
+    framebuffer: Option<WebGlFramebuffer>,
+    active_texture: u32,
+    texture: Option<WebGlTexture>,
+    position_attrib_enabled: bool,
+    position_attrib: AttribPointer,
+}
+
+/// The pointer of a vertex attribute, as set by `vertex_attrib_pointer`.
+struct AttribPointer {
+    buffer: Option<WebGlBuffer>,
+    size: i32,
+    type_: u32,
+    normalized: bool,
+    stride: i32,
+    offset: f64,
+}
+
+impl AttribPointer {
+    fn capture(context: &WebGlRenderingContext, index: u32) -> AttribPointer {
+        let attrib = |name| context.get_vertex_attrib(index, name).unwrap_or(JsValue::NULL);
+        let number = |name| attrib(name).as_f64().unwrap_or(0.0);
+        AttribPointer {
+            buffer: attrib(WebGlRenderingContext::VERTEX_ATTRIB_ARRAY_BUFFER_BINDING).dyn_into().ok(),
+            size: number(WebGlRenderingContext::VERTEX_ATTRIB_ARRAY_SIZE) as i32,
+            type_: number(WebGlRenderingContext::VERTEX_ATTRIB_ARRAY_TYPE) as u32,
+            normalized: attrib(WebGlRenderingContext::VERTEX_ATTRIB_ARRAY_NORMALIZED).is_truthy(),
+            stride: number(WebGlRenderingContext::VERTEX_ATTRIB_ARRAY_STRIDE) as i32,
+            offset: context.get_vertex_attrib_offset(index, WebGlRenderingContext::VERTEX_ATTRIB_ARRAY_POINTER),
+        }
+    }
+
+    /// Points attribute `index` back at the captured buffer, which leaves it
+    /// bound to ARRAY_BUFFER.
+    fn restore(&self, context: &WebGlRenderingContext, index: u32) {
+        context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, self.buffer.as_ref());
+        // Without a buffer only a zero offset is valid, which is the default.
+        if self.buffer.is_some() || self.offset == 0.0 {
+            context.vertex_attrib_pointer_with_f64(index, self.size, self.type_, self.normalized, self.stride, self.offset);
+        }
+    }
+}
+
+impl GlState {
+    pub fn capture(context: &WebGlRenderingContext) -> GlState {
+        let parameter = |name| context.get_parameter(name).unwrap_or(JsValue::NULL);
+        let enum_parameter = |name| parameter(name).as_f64().unwrap_or(0.0) as u32;
+
+        GlState {
+            blend: context.is_enabled(WebGlRenderingContext::BLEND),
+            scissor_test: context.is_enabled(WebGlRenderingContext::SCISSOR_TEST),
+            cull_face: context.is_enabled(WebGlRenderingContext::CULL_FACE),
+            cull_face_mode: enum_parameter(WebGlRenderingContext::CULL_FACE_MODE),
+            front_face: enum_parameter(WebGlRenderingContext::FRONT_FACE),
+            blend_func: [
+                enum_parameter(WebGlRenderingContext::BLEND_SRC_RGB),
+                enum_parameter(WebGlRenderingContext::BLEND_DST_RGB),
+                enum_parameter(WebGlRenderingContext::BLEND_SRC_ALPHA),
+                enum_parameter(WebGlRenderingContext::BLEND_DST_ALPHA),
+            ],
+            clear_color: js_sys::Float32Array::new(&parameter(WebGlRenderingContext::COLOR_CLEAR_VALUE)).to_vec(),
+            viewport: js_sys::Int32Array::new(&parameter(WebGlRenderingContext::VIEWPORT)).to_vec(),
+            scissor_box: js_sys::Int32Array::new(&parameter(WebGlRenderingContext::SCISSOR_BOX)).to_vec(),
+            program: parameter(WebGlRenderingContext::CURRENT_PROGRAM).dyn_into().ok(),
+            array_buffer: parameter(WebGlRenderingContext::ARRAY_BUFFER_BINDING).dyn_into().ok(),
+            element_array_buffer: parameter(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER_BINDING).dyn_into().ok(),
+            framebuffer: parameter(WebGlRenderingContext::FRAMEBUFFER_BINDING).dyn_into().ok(),
+            active_texture: enum_parameter(WebGlRenderingContext::ACTIVE_TEXTURE),
+            texture: parameter(WebGlRenderingContext::TEXTURE_BINDING_2D).dyn_into().ok(),
+            position_attrib_enabled: context
+                .get_vertex_attrib(0, WebGlRenderingContext::VERTEX_ATTRIB_ARRAY_ENABLED)
+                .is_ok_and(|enabled| enabled.is_truthy()),
+            position_attrib: AttribPointer::capture(context, 0),
+        }
+    }
+
+    pub fn restore(&self, context: &WebGlRenderingContext) {
+        set_enabled(context, WebGlRenderingContext::BLEND, self.blend);
+        set_enabled(context, WebGlRenderingContext::SCISSOR_TEST, self.scissor_test);
+        set_enabled(context, WebGlRenderingContext::CULL_FACE, self.cull_face);
+        context.cull_face(self.cull_face_mode);
+        context.front_face(self.front_face);
+        let [src_rgb, dst_rgb, src_alpha, dst_alpha] = self.blend_func;
+        context.blend_func_separate(src_rgb, dst_rgb, src_alpha, dst_alpha);
+        if let [r, g, b, a] = self.clear_color[..] {
+            context.clear_color(r, g, b, a);
+        }
+        if let [x, y, width, height] = self.viewport[..] {
+            context.viewport(x, y, width, height);
+        }
+        if let [x, y, width, height] = self.scissor_box[..] {
+            context.scissor(x, y, width, height);
+        }
+        context.use_program(self.program.as_ref());
+        self.position_attrib.restore(context, 0);
+        context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, self.array_buffer.as_ref());
+        context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, self.element_array_buffer.as_ref());
+        context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, self.framebuffer.as_ref());
+        context.active_texture(self.active_texture);
+        context.bind_texture(WebGlRenderingContext::TEXTURE_2D, self.texture.as_ref());
+        if self.position_attrib_enabled {
+            context.enable_vertex_attrib_array(0);
+        } else {
+            context.disable_vertex_attrib_array(0);
+        }
+    }
+}
+
+fn set_enabled(context: &WebGlRenderingContext, capability: u32, enabled: bool) {
+    if enabled {
+        context.enable(capability);
+    } else {
+        context.disable(capability);
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::VecDeque;
+use wasm_bindgen::JsCast;
+use web_sys::{ExtDisjointTimerQuery, WebGl2RenderingContext, WebGlQuery, WebGlRenderingContext};
+
+/// Frames whose timings may be pending at once. Results usually arrive a
+/// frame or two late, and frames aren't timed while this many are pending.
+const MAX_PENDING_QUERIES: usize = 4;
+
+/// The timer query API of the context: the WebGL 1 extension object, or the
+/// query methods of WebGL 2 enabled by EXT_disjoint_timer_query_webgl2.
+enum TimerApi {
+    WebGl1(ExtDisjointTimerQuery),
+    WebGl2(WebGl2RenderingContext),
+}
+
+/// Measures the GPU time of frames with timer queries.
+pub struct GpuTimer {
+    api: TimerApi,
+    context: WebGlRenderingContext,
+    pending: VecDeque<WebGlQuery>,
+    /// Whether the current frame has an active query.
+    active: bool,
+}
+
+impl GpuTimer {
+    /// A timer for `context`, or None when it doesn't support timer queries.
+    pub fn new(context: &WebGlRenderingContext) -> Option<GpuTimer> {
+        let api = if let Some(context) = context.dyn_ref::<WebGl2RenderingContext>() {
+            context.get_extension("EXT_disjoint_timer_query_webgl2").ok().flatten()?;
+            TimerApi::WebGl2(context.clone())
+        } else {
+            let extension = context.get_extension("EXT_disjoint_timer_query").ok().flatten()?;
+            TimerApi::WebGl1(extension.unchecked_into())
+        };
+        Some(GpuTimer { api, context: context.clone(), pending: VecDeque::new(), active: false })
+    }
+
+    /// Starts timing a frame, unless too many results are still pending.
+    pub fn begin_frame(&mut self) {
+        if self.pending.len() >= MAX_PENDING_QUERIES {
+            return;
+        }
+        let query = match &self.api {
+            TimerApi::WebGl1(extension) => extension.create_query_ext(),
+            TimerApi::WebGl2(context) => context.create_query(),
+        };
+        if let Some(query) = query {
+            match &self.api {
+                TimerApi::WebGl1(extension) => extension.begin_query_ext(ExtDisjointTimerQuery::TIME_ELAPSED_EXT, &query),
+                TimerApi::WebGl2(context) => context.begin_query(ExtDisjointTimerQuery::TIME_ELAPSED_EXT, &query),
+            }
+            self.pending.push_back(query);
+            self.active = true;
+        }
+    }
+
+    pub fn end_frame(&mut self) {
+        if !self.active {
+            return;
+        }
+        match &self.api {
+            TimerApi::WebGl1(extension) => extension.end_query_ext(ExtDisjointTimerQuery::TIME_ELAPSED_EXT),
+            TimerApi::WebGl2(context) => context.end_query(ExtDisjointTimerQuery::TIME_ELAPSED_EXT),
+        }
+        self.active = false;
+    }
+
+    /// The GPU seconds of the oldest timed frame once its result is
+    /// available, after `end_frame`. Results are dropped when the GPU reports
+    /// a disjoint operation, like a context switch, which makes them
+    /// unreliable.
+    pub fn poll(&mut self) -> Option<f64> {
+        let disjoint = self.context
+            .get_parameter(ExtDisjointTimerQuery::GPU_DISJOINT_EXT)
+            .is_ok_and(|disjoint| disjoint.is_truthy());
+        if disjoint {
+            while let Some(query) = self.pending.pop_front() {
+                self.delete(&query);
+            }
+            return None;
+        }
+
+        if !self.query_value(self.pending.front()?, true).is_truthy() {
+            return None;
+        }
+        let query = self.pending.pop_front()?;
+        let nanoseconds = self.query_value(&query, false).as_f64();
+        self.delete(&query);
+        nanoseconds.map(|nanoseconds| nanoseconds / 1e9)
+    }
+
+    /// Whether the result is available, or the result itself.
+    fn query_value(&self, query: &WebGlQuery, availability: bool) -> wasm_bindgen::JsValue {
+        match &self.api {
+            TimerApi::WebGl1(extension) => extension.get_query_object_ext(
+                query,
+                if availability { ExtDisjointTimerQuery::QUERY_RESULT_AVAILABLE_EXT } else { ExtDisjointTimerQuery::QUERY_RESULT_EXT }
+            ),
+            TimerApi::WebGl2(context) => context.get_query_parameter(
+                query,
+                if availability { WebGl2RenderingContext::QUERY_RESULT_AVAILABLE } else { WebGl2RenderingContext::QUERY_RESULT }
+            ),
+        }
+    }
+
+    fn delete(&self, query: &WebGlQuery) {
+        match &self.api {
+            TimerApi::WebGl1(extension) => extension.delete_query_ext(Some(query)),
+            TimerApi::WebGl2(context) => context.delete_query(Some(query)),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use crate::field::FieldComputer;
+
+/// An outline traced out of the field, in normalized canvas coordinates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contour {
+    pub points: Vec<(f32, f32)>,
+    /// Whether the last point joins the first. Contours leaving the region
+    /// stay open.
+    pub closed: bool,
+}
+
+/// A crossing of the threshold on a grid edge: horizontal edges run right
+/// from sample (column, row), vertical ones down from it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Crossing {
+    vertical: bool,
+    column: u32,
+    row: u32,
+}
+
+const TOP: usize = 0;
+const RIGHT: usize = 1;
+const BOTTOM: usize = 2;
+const LEFT: usize = 3;
+
+/// Edge pairs the contour crosses a cell on, indexed by which corners are
+/// inside (bit 0 top left, 1 top right, 2 bottom right, 3 bottom left). The
+/// saddles 5 and 10 are resolved separately.
+const SEGMENTS: [&[(usize, usize)]; 16] = [
+    &[],
+    &[(LEFT, TOP)],
+    &[(TOP, RIGHT)],
+    &[(LEFT, RIGHT)],
+    &[(RIGHT, BOTTOM)],
+    &[],
+    &[(TOP, BOTTOM)],
+    &[(LEFT, BOTTOM)],
+    &[(BOTTOM, LEFT)],
+    &[(TOP, BOTTOM)],
+    &[],
+    &[(RIGHT, BOTTOM)],
+    &[(LEFT, RIGHT)],
+    &[(TOP, RIGHT)],
+    &[(LEFT, TOP)],
+    &[],
+];
+
+/// Traces the outlines where `field` crosses `threshold`, by marching squares
+/// over a `resolution` x `resolution` grid of cells with the crossings
+/// linearly interpolated along the cell edges.
+pub fn trace_contours(field: &FieldComputer, threshold: f32, resolution: u32) -> Vec<Contour> {
+    let resolution = resolution.max(1);
+    let samples = (resolution + 1) as usize;
+    let position = |index: u32| index as f32 / resolution as f32;
+    let mut values = Vec::with_capacity(samples * samples);
+    for row in 0..=resolution {
+        for column in 0..=resolution {
+            values.push(field.field_at(position(column), position(row)));
+        }
+    }
+    let value = |column: u32, row: u32| values[row as usize * samples + column as usize];
+
+    let mut points = HashMap::new();
+    let mut links: HashMap<Crossing, Vec<Crossing>> = HashMap::new();
+    for row in 0..resolution {
+        for column in 0..resolution {
+            let corners = [
+                value(column, row),
+                value(column + 1, row),
+                value(column + 1, row + 1),
+                value(column, row + 1),
+            ];
+            let case = corners.iter().enumerate()
+                .filter(|(_, &corner)| corner >= threshold)
+                .fold(0, |case, (bit, _)| case | 1 << bit);
+            let center_inside = corners.iter().sum::<f32>() / 4.0 >= threshold;
+            let segments: &[(usize, usize)] = match (case, center_inside) {
+                (5, true) | (10, false) => &[(TOP, RIGHT), (BOTTOM, LEFT)],
+                (5, false) | (10, true) => &[(LEFT, TOP), (RIGHT, BOTTOM)],
+                _ => SEGMENTS[case],
+            };
+            for &(from, to) in segments {
+                let [from, to] = [from, to].map(|edge| {
+                    let (crossing, a, b) = match edge {
+                        TOP => (Crossing { vertical: false, column, row }, 0, 1),
+                        RIGHT => (Crossing { vertical: true, column: column + 1, row }, 1, 2),
+                        BOTTOM => (Crossing { vertical: false, column, row: row + 1 }, 3, 2),
+                        _ => (Crossing { vertical: true, column, row }, 0, 3),
+                    };
+                    points.entry(crossing).or_insert_with(|| {
+                        let t = ((threshold - corners[a]) / (corners[b] - corners[a])).clamp(0.0, 1.0);
+                        let x = position(crossing.column);
+                        let y = position(crossing.row);
+                        let step = t / resolution as f32;
+                        if crossing.vertical { (x, y + step) } else { (x + step, y) }
+                    });
+                    crossing
+                });
+                links.entry(from).or_default().push(to);
+                links.entry(to).or_default().push(from);
+            }
+        }
+    }
+
+    // Open contours start at a crossing with a single link, on the border of
+    // the region, so walk those before the closed loops.
+    let mut starts: Vec<Crossing> = links.keys().copied().collect();
+    starts.sort_by_key(|crossing| (links[crossing].len(), crossing.row, crossing.column, crossing.vertical));
+    let mut contours = vec![];
+    for start in starts {
+        if links[&start].is_empty() {
+            continue;
+        }
+        let mut contour = Contour { points: vec![points[&start]], closed: false };
+        let mut current = start;
+        while let Some(next) = links.get_mut(&current).and_then(|next| next.pop()) {
+            let back = links.get_mut(&next).unwrap();
+            if let Some(index) = back.iter().position(|&crossing| crossing == current) {
+                back.swap_remove(index);
+            }
+            if next == start {
+                contour.closed = true;
+                break;
+            }
+            contour.points.push(points[&next]);
+            current = next;
+        }
+        contours.push(contour);
+    }
+    contours
+}
+
+/// SVG path data for `contours` scaled to a `width` x `height` region, one
+/// subpath per contour with the closed ones ending in `Z`.
+pub fn to_svg_path(contours: &[Contour], width: f32, height: f32) -> String {
+    contours.iter()
+        .map(|contour| {
+            let mut path = contour.points.iter().enumerate()
+                .map(|(index, &(x, y))| {
+                    let command = if index == 0 { 'M' } else { 'L' };
+                    format!("{}{:.2} {:.2}", command, x * width, y * height)
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            if contour.closed {
+                path.push_str(" Z");
+            }
+            path
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,48 @@
+/// A pass composited onto the canvas, in the order set by `set_layer_order`.
+/// The clear strategy runs before all of them and the blur after.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layer {
+    Blobs,
+    /// The paint mode's accumulation buffer, added onto what's below.
+    Paint,
+}
+
+impl Layer {
+    pub fn from_name(name: &str) -> Option<Layer> {
+        match name {
+            "blobs" => Some(Layer::Blobs),
+            "paint" => Some(Layer::Paint),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Layer::Blobs => "blobs",
+            Layer::Paint => "paint",
+        }
+    }
+}
+
+pub fn default_layer_order() -> Vec<Layer> {
+    vec![Layer::Blobs, Layer::Paint]
+}
+
+/// Parses layer names from bottom to top, along with warnings about the
+/// names skipped for being unknown or repeated. Fails without "blobs". Layers
+/// left out aren't drawn.
+pub fn parse_layer_order(names: &[String]) -> Result<(Vec<Layer>, Vec<String>), String> {
+    let mut order = vec![];
+    let mut warnings = vec![];
+    for name in names {
+        match Layer::from_name(name) {
+            Some(layer) if order.contains(&layer) => warnings.push(format!("Layer \"{}\" is listed more than once.", name)),
+            Some(layer) => order.push(layer),
+            None => warnings.push(format!("Unknown layer \"{}\".", name)),
+        }
+    }
+    if !order.contains(&Layer::Blobs) {
+        return Err("The layer order must include \"blobs\".".to_string());
+    }
+    Ok((order, warnings))
+}
@@ -1,8 +1,79 @@
 mod utils;
+mod config;
+mod metaball;
+mod audio;
+mod effect;
+mod viewport;
+mod capabilities;
+mod easing;
+mod clock;
+mod export;
+mod ripple;
+mod physics;
+mod clear;
+mod stats;
+mod tiles;
+mod origin;
+mod framebuffer;
+mod blur;
+mod field;
+mod pointer;
+mod params;
+mod shading;
+mod glstate;
+mod upscale;
+mod paint;
+mod ambient;
+mod gpu_timer;
+mod layers;
+mod tail;
+mod isolines;
+mod theme;
+mod emitter;
+mod geometry;
+mod smoothing;
+mod pulse;
+
+pub use config::{apply_precision_fallback, Config, PointerTarget};
+pub use metaball::{check_capacity, effective_radius, melt_factor, Lifetime, Metaball, Shape, ShapeKind, LIFETIME_FADE, MAX_METABALLS};
+pub use audio::{apply_levels, band_levels};
+pub use effect::Effect;
+pub use viewport::{snap_to_grid, Region};
+pub use capabilities::Capabilities;
+pub use easing::{ease_in_out, ease_toward, fade_in_opacity};
+pub use clock::{current_time, set_test_mode, set_test_time, test_time, Clock, HoverFreeze, SpeedTween, MAX_FRAME_GAP};
+pub use clear::{ClearStrategy, FrameClear};
+pub use stats::{FrameStats, RenderStats};
+pub use pointer::{speed_scale, wheel_speed, PointerTracker, WHEEL_SPEED_STEP};
+pub use params::{params_uniform, MAX_PARAMS};
+pub use shading::{apply_gamma, quantize, surface_color, EDGE_BAND, FILL_COLOR};
+pub use glstate::GlState;
+pub use tail::PointerTail;
+pub use layers::{default_layer_order, parse_layer_order, Layer};
+pub use ambient::{ambient_balls, AMBIENT_BALL_COUNT};
+pub use paint::{dab_value, Paint, DAB_INTENSITY, DAB_RADIUS};
+pub use upscale::{crisp_coverage, edge_alpha, edge_width, nearest_texel, render_size, scaled_size, EDGE_SCALE};
+pub use field::{bounding_box, field_intensity, smooth_min, FieldComputer, LOG_SCALE_DECADES, SCENE_UNIT, SMOOTHNESS, THRESHOLD};
+pub use pulse::{apply_pulse, combined_pulse, ColorPulse, PULSE_STRENGTH};
+pub use smoothing::{Filter, PointerFilter, Smoothing, SmoothingKind, ONE_EURO_DERIVATIVE_CUTOFF};
+pub use geometry::{front_facing, CullMode, QUAD_INDICES, QUAD_POSITIONS};
+pub use emitter::{check_emitter, Emitter, Rng, DEFAULT_SEED, EMITTED_LIFETIME, EMITTED_RADIUS, MAX_EMIT_RATE};
+pub use theme::{time_of_day_palette, Palette, DAWN, DAY, DEFAULT_PALETTE, DUSK, NIGHT};
+pub use isolines::{to_svg_path, trace_contours, Contour};
+pub use blur::{gaussian_weights, MAX_BLUR_TAPS};
+pub use physics::{collide, integrate, integrate_with_collisions, substeps, COLLISION_SLOP, MAX_SUBSTEPS};
+pub use origin::{from_normalized, to_normalized, Origin};
+pub use tiles::{bin_balls, limit_bins, tile_region, FIELD_REACH, MAX_TILED_METABALLS, MAX_TILES_PER_SIDE};
+pub use ripple::{amplitude as ripple_amplitude, lifetime as ripple_lifetime, RippleParams, Ripples, MAX_RIPPLES};
+
+use effect::State;
+use framebuffer::RenderTarget;
+pub use framebuffer::{FrameResources, QuadBuffers};
+use gpu_timer::GpuTimer;
 
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use web_sys::{WebGlRenderingContext, WebGlShader, WebGlBuffer, WebGlProgram, WebGlUniformLocation};
+use web_sys::{WebGlRenderingContext, WebGlShader, WebGlProgram, WebGlUniformLocation};
 use std::rc::{Rc};
 use std::cell::{RefCell};
 
@@ -19,39 +90,151 @@ extern "C" {
     #[wasm_bindgen(js_namespace = console)]
     fn log(s: &str);
 
+    #[wasm_bindgen(js_namespace = console)]
+    fn warn(s: &str);
+
     #[wasm_bindgen(js_namespace = console)]
     fn error(s: &str);
 }
 
 static FRAGMENT_SHADER: &'static str = r#"
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+precision highp float;
+#else
 precision mediump float;
+#endif
 uniform float time;
 uniform vec2  mouse;
 uniform vec2  resolution;
+uniform vec2  offset; // bottom-left corner of the rendered region
+uniform float dithering;
+uniform float backgroundAlpha;
+uniform vec3  balls[MAX_METABALLS]; // x, y, radius
+uniform vec4  shapes[MAX_METABALLS]; // kind (0 circle, 1 ellipse, 2 capsule), shape parameters
+uniform int   ballCount;
+uniform bool  defaultScene; // no balls were added, so the mouse-driven spheres are shown
+uniform vec4  ripples[MAX_RIPPLES]; // x, y, age, amplitude
+uniform vec2  rippleParams;         // wavelength, speed
+uniform float params[MAX_PARAMS];   // free for experiments, see set_params
+uniform float celLevels;            // bands of the lighting, below 2 for smooth shading
+uniform float mergeSmoothness;      // k of the polynomial smooth minimum, 0 to add up the field
+uniform float mouseBallScale;       // radius factor of the mouse-driven spheres
+uniform vec3  edgeColor;            // the rim color, fading to the fill inside
+uniform vec3  fillColor;
+uniform vec3  backgroundColor;
+uniform float gamma;                // the output is raised to 1 / gamma
+uniform float opacity;              // fades the blobs over the background
+uniform vec4  pulse;                // accent color the output is pulled toward, and how far
+uniform bool  invertField;          // fills the outside of the blobs instead of the inside
+uniform bool  fieldPass;            // writes the lit color and the edge distance for the upscaling pass
 
 const float sphereSize = 0.5; // 球の半径
+const float smoothness = 2.0;
+const float sceneUnit = 4.0; // the shorter canvas side spans 4 units on the z = 0 plane
+
+float rand(vec2 co){
+    return fract(sin(dot(co, vec2(12.9898, 78.233))) * 43758.5453);
+}
 
 float smoothMin(float d1, float d2, float k){
     float h = exp(-k * d1) + exp(-k * d2);
     return -log(h) / k;
 }
 
-float distanceFunc(vec3 p){
+// normalized canvas coordinates (top-left origin) to the z = 0 plane
+vec2 toScene(vec2 position){
+    vec2 p = vec2(position.x * 2.0 - 1.0, 1.0 - position.y * 2.0) * resolution / min(resolution.x, resolution.y);
+    return p * 2.0;
+}
+
+// distance from p to the outline of a ball, see metaball::Shape
+float ballDistance(vec3 p, vec3 ball, vec4 shape){
+    vec2 d = p.xy - toScene(ball.xy);
+    if(shape.x > 1.5){
+        // capsule: measured from the nearest point of the segment
+        vec2 axis = vec2(cos(shape.z), sin(shape.z)) * shape.y * sceneUnit * 0.5;
+        d -= axis * clamp(dot(d, axis) / max(dot(axis, axis), 0.000001), -1.0, 1.0);
+    }else if(shape.x > 0.5){
+        // ellipse: a sphere scaled along the axes, as thick as it is narrow
+        vec2 radii = max(shape.yz * sceneUnit, vec2(0.000001));
+        float shorter = min(radii.x, radii.y);
+        return (length(vec3(d / radii, p.z / shorter)) - 1.0) * shorter;
+    }
+    return length(vec3(d, p.z)) - ball.z * sceneUnit;
+}
+
+// matches field::smooth_min
+float smoothMinPoly(float a, float b, float k){
+    float h = clamp(0.5 + 0.5 * (b - a) / k, 0.0, 1.0);
+    return mix(b, a, h) - k * h * (1.0 - h);
+}
+
+float ballsDistanceFunc(vec3 p){
+    if(mergeSmoothness > 0.0){
+        float d = 1000.0;
+        for(int i = 0; i < MAX_METABALLS; i++){
+            if(i >= ballCount) break;
+            d = smoothMinPoly(d, ballDistance(p, balls[i], shapes[i]), mergeSmoothness);
+        }
+        return d;
+    }
+    float h = 0.0;
+    for(int i = 0; i < MAX_METABALLS; i++){
+        if(i >= ballCount) break;
+        h += exp(-smoothness * ballDistance(p, balls[i], shapes[i]));
+    }
+    if(h <= 0.0) return 1000.0;
+    // overlapping balls can overflow the sum
+    return -log(min(h, 1e30)) / smoothness;
+}
+
+// waves travelling outward from the ripple origins, localized around the wave front
+float rippleFunc(vec3 p){
+    float h = 0.0;
+    for(int i = 0; i < MAX_RIPPLES; i++){
+        vec4 ripple = ripples[i];
+        if(ripple.w <= 0.0) continue;
+        float d = length(p.xy - toScene(ripple.xy));
+        float front = d - rippleParams.y * sceneUnit * ripple.z;
+        h += ripple.w * exp(-abs(front) * 2.0) * sin(front * 6.2831853 / (rippleParams.x * sceneUnit));
+    }
+    return h * 0.1;
+}
+
+float fieldDistanceFunc(vec3 p){
+    if(!defaultScene){
+        return ballsDistanceFunc(p);
+    }
+
     float normalizedMouseX = (mouse.x * 2.0 - 1.0);
     float distance = abs(normalizedMouseX) * 3.0;
 
     vec3 sphere1Center = vec3(distance / 2.0, 0.0, 0.0);
     vec3 sphere2Center = vec3(-distance / 2.0, 0.0, 0.0);
 
-    float sphere1 = length(p - sphere1Center) - sphereSize;
-    float sphere2 = length(p - sphere2Center) - sphereSize;
+    float sphere1 = length(p - sphere1Center) - sphereSize * mouseBallScale;
+    float sphere2 = length(p - sphere2Center) - sphereSize * mouseBallScale;
+
+    return smoothMin(sphere1, sphere2, smoothness);
+}
+
+// matches upscale::EDGE_SCALE
+const float edgeScale = 4.0;
+// matches shading::EDGE_BAND
+const float edgeBand = 0.3;
+
+float quantize(float intensity, float levels){
+    if(levels < 2.0) return intensity;
+    return min(floor(clamp(intensity, 0.0, 1.0) * levels), levels - 1.0) / (levels - 1.0);
+}
 
-    return smoothMin(sphere1, sphere2, 2.0);
+float distanceFunc(vec3 p){
+    return fieldDistanceFunc(p) + rippleFunc(p);
 }
 
 void main(void){
     // fragment position
-    vec2 p = (gl_FragCoord.xy * 2.0 - resolution) / min(resolution.x, resolution.y);
+    vec2 p = ((gl_FragCoord.xy - offset) * 2.0 - resolution) / min(resolution.x, resolution.y);
     
     // camera
     vec3 cPos = vec3(0.0,  0.0,  2.0);
@@ -73,18 +256,102 @@ void main(void){
         rPos = cPos + ray * rLen;
     }
     
+    // lighting effect
+    vec3 ambientLight = vec3(0.3, 0.3, 0.3);
+    vec3 directionalLightColor = vec3(1, 1, 1);
+    vec3 directionalVector = normalize(vec3(0.1, 0.8, 0.75));
+    float directional = quantize(max(dot(rPos.xyz, directionalVector), 0.0), celLevels);
+    vec3 vLighting = ambientLight + (directionalLightColor * directional);
+
+    // the distance at the plane through the ball centers, which the ray
+    // reaches at twice p, ramps smoothly across the silhouette
+    float edge = distanceFunc(vec3(p * 2.0, 0.0));
+    bool hit = abs(distance) < 0.001;
+    if(invertField){
+        // the holes are flat, so the solid outside is left unlit
+        edge = -edge;
+        hit = edge < 0.0;
+        vLighting = vec3(1.0);
+    }
+    vec3 surface = mix(edgeColor, fillColor, smoothstep(0.0, edgeBand, -edge));
+    vec3 color = mix(pow(max(surface * vLighting, 0.0), vec3(1.0 / gamma)), pulse.rgb, pulse.a);
+    vec3 background = mix(backgroundColor, pulse.rgb, pulse.a);
+
     // hit check
-    if(abs(distance) < 0.001){
-        // lighting effect
-        highp vec3 ambientLight = vec3(0.3, 0.3, 0.3);
-        highp vec3 directionalLightColor = vec3(1, 1, 1);
-        highp vec3 directionalVector = normalize(vec3(0.1, 0.8, 0.75));
-        highp float directional = max(dot(rPos.xyz, directionalVector), 0.0);
-        highp vec3 vLighting = ambientLight + (directionalLightColor * directional);
-        gl_FragColor = vec4(vec3(1, 1, 1) * vLighting, 1);
+    if(fieldPass){
+        gl_FragColor = vec4(color, clamp(0.5 - edge * edgeScale, 0.0, 1.0) * opacity);
+        return;
+    }else if(hit){
+        gl_FragColor = vec4(mix(background, color, opacity), mix(backgroundAlpha, 1.0, opacity));
     }else{
-        gl_FragColor = vec4(vec3(0.0), 1.0);
+        gl_FragColor = vec4(background, backgroundAlpha);
     }
+
+    // dithering
+    gl_FragColor.rgb += dithering * (rand(gl_FragCoord.xy) - 0.5) / 255.0;
+}
+"#;
+
+static FADE_FRAGMENT_SHADER: &str = r#"
+precision mediump float;
+uniform vec4 color;
+
+void main(void){
+    gl_FragColor = color;
+}
+"#;
+
+static BLUR_FRAGMENT_SHADER: &str = r#"
+precision mediump float;
+uniform sampler2D source;
+uniform vec2  resolution;
+uniform vec2  direction; // (1, 0) for the horizontal pass, (0, 1) for the vertical one
+uniform float weights[MAX_BLUR_TAPS];
+uniform int   taps;
+
+void main(void){
+    vec2 uv = gl_FragCoord.xy / resolution;
+    vec2 texel = direction / resolution;
+    vec4 color = texture2D(source, uv) * weights[0];
+    for(int i = 1; i < MAX_BLUR_TAPS; i++){
+        if(i >= taps) break;
+        vec2 offset = texel * float(i);
+        color += (texture2D(source, uv + offset) + texture2D(source, uv - offset)) * weights[i];
+    }
+    gl_FragColor = color;
+}
+"#;
+
+static UPSCALE_FRAGMENT_SHADER: &str = r#"
+precision mediump float;
+uniform sampler2D source;
+uniform vec2  resolution;
+uniform vec2  offset;
+uniform bool  crisp;          // thresholds the edge distance of the field pass
+uniform float edgeWidth;
+uniform float backgroundAlpha;
+uniform vec3  backgroundColor;
+
+void main(void){
+    vec4 color = texture2D(source, (gl_FragCoord.xy - offset) / resolution);
+    if(crisp){
+        float coverage = smoothstep(0.5 - edgeWidth, 0.5 + edgeWidth, color.a);
+        gl_FragColor = vec4(mix(backgroundColor, color.rgb, coverage), mix(backgroundAlpha, 1.0, coverage));
+    }else{
+        gl_FragColor = color;
+    }
+}
+"#;
+
+static DAB_FRAGMENT_SHADER: &str = r#"
+precision mediump float;
+uniform vec2  center; // in gl_FragCoord pixels
+uniform float radius;
+uniform float intensity;
+
+void main(void){
+    float d = length(gl_FragCoord.xy - center) / max(radius, 0.000001);
+    gl_FragColor = vec4(vec3(intensity * exp(-d * d * 2.0)), 1.0);
 }
 "#;
 
@@ -96,25 +363,54 @@ void main(void){
 }
 "#;
 
+fn fragment_shader_source() -> String {
+    format!(
+        "#define MAX_METABALLS {}\n#define MAX_RIPPLES {}\n#define MAX_PARAMS {}\n{}",
+        MAX_METABALLS, MAX_RIPPLES, MAX_PARAMS, FRAGMENT_SHADER
+    )
+}
+
 #[wasm_bindgen]
-pub fn start() -> Result<(), JsValue> {
+pub fn start() -> Result<Effect, JsValue> {
+    start_with_config(Config::default())
+}
+
+#[wasm_bindgen]
+pub fn start_with_config(mut config: Config) -> Result<Effect, JsValue> {
     let canvas = get_canvas_element_by_id("canvas")?;
-    let context = get_webgl_context(&canvas)?;
+    let (context, context_id) = get_webgl_context(&canvas, config.context_ids())?;
+
+    let highp_precision = context
+        .get_shader_precision_format(WebGlRenderingContext::FRAGMENT_SHADER, WebGlRenderingContext::HIGH_FLOAT)
+        .map_or(0, |format| format.precision());
+    if apply_precision_fallback(&mut config, highp_precision) {
+        warn("highp isn't supported in fragment shaders. Dithering is enabled to reduce banding.");
+    }
 
-    let mouse_x = Rc::new(RefCell::new(0));
-    let mouse_y = Rc::new(RefCell::new(0));
     let canvas_w = canvas.client_width();
     let canvas_h = canvas.client_height();
 
-    {
-        let mouse_x = mouse_x.clone();
-        let mouse_y = mouse_y.clone();
-        add_event_listener(&canvas, "mousemove", move |event| {
-            let mouse_event = event.dyn_into::<web_sys::MouseEvent>().unwrap();
-            *mouse_x.borrow_mut() = mouse_event.offset_x();
-            *mouse_y.borrow_mut() = mouse_event.offset_y();
-        })?;
-    }
+    let fade_program = init_program(&context, FADE_FRAGMENT_SHADER)?;
+    let ul_fade_color = context.get_uniform_location(&fade_program, "color");
+
+    let blur_pass = BlurPass::new(&context)?;
+
+    let upscale_program = init_program(&context, UPSCALE_FRAGMENT_SHADER)?;
+    let ul_upscale_resolution = context.get_uniform_location(&upscale_program, "resolution");
+    let ul_upscale_offset = context.get_uniform_location(&upscale_program, "offset");
+    let ul_upscale_crisp = context.get_uniform_location(&upscale_program, "crisp");
+    let ul_upscale_edge_width = context.get_uniform_location(&upscale_program, "edgeWidth");
+    let ul_upscale_background_alpha = context.get_uniform_location(&upscale_program, "backgroundAlpha");
+    let ul_upscale_background_color = context.get_uniform_location(&upscale_program, "backgroundColor");
+    // the scene at the reduced resolution, while the resolution scale is below 1
+    let mut low_res_target: Option<RenderTarget> = None;
+
+    let dab_program = init_program(&context, DAB_FRAGMENT_SHADER)?;
+    let ul_dab_center = context.get_uniform_location(&dab_program, "center");
+    let ul_dab_radius = context.get_uniform_location(&dab_program, "radius");
+    let ul_dab_intensity = context.get_uniform_location(&dab_program, "intensity");
+    // the accumulated paint, in paint mode
+    let mut paint_target: Option<RenderTarget> = None;
 
     let shader_program = match init_shaders(&context) {
         Ok(s) => s,
@@ -124,58 +420,637 @@ pub fn start() -> Result<(), JsValue> {
     let ul_time = context.get_uniform_location(&shader_program, "time");
     let ul_mouse = context.get_uniform_location(&shader_program, "mouse");
     let ul_resolution = context.get_uniform_location(&shader_program, "resolution");
+    let ul_offset = context.get_uniform_location(&shader_program, "offset");
 
-    let (position_buffer, index_buffer) = init_buffers(&context);
-    let attrib_location = context.get_attrib_location(&shader_program, "position") as u32;
+    let ul_balls = context.get_uniform_location(&shader_program, "balls");
+    let ul_shapes = context.get_uniform_location(&shader_program, "shapes");
+    let ul_ball_count = context.get_uniform_location(&shader_program, "ballCount");
+    let ul_default_scene = context.get_uniform_location(&shader_program, "defaultScene");
 
-    context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&position_buffer));
-    context.enable_vertex_attrib_array(attrib_location);
-    context.vertex_attrib_pointer_with_i32(
-        attrib_location,
-        3,
-        WebGlRenderingContext::FLOAT,
-        false,
-        0,
-        0
-    );
-    context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&index_buffer));
+    let ul_ripples = context.get_uniform_location(&shader_program, "ripples");
+    let ul_ripple_params = context.get_uniform_location(&shader_program, "rippleParams");
+    let ul_params = context.get_uniform_location(&shader_program, "params");
+    let ul_cel_levels = context.get_uniform_location(&shader_program, "celLevels");
+    let ul_field_pass = context.get_uniform_location(&shader_program, "fieldPass");
+    let ul_invert_field = context.get_uniform_location(&shader_program, "invertField");
+    let ul_gamma = context.get_uniform_location(&shader_program, "gamma");
+    let ul_merge_smoothness = context.get_uniform_location(&shader_program, "mergeSmoothness");
+    let ul_opacity = context.get_uniform_location(&shader_program, "opacity");
+    let ul_pulse = context.get_uniform_location(&shader_program, "pulse");
+    let ul_edge_color = context.get_uniform_location(&shader_program, "edgeColor");
+    let ul_fill_color = context.get_uniform_location(&shader_program, "fillColor");
+    let ul_background_color = context.get_uniform_location(&shader_program, "backgroundColor");
+    let ul_mouse_ball_scale = context.get_uniform_location(&shader_program, "mouseBallScale");
 
-    context.clear_color(0.0, 0.0, 0.0, 1.0);
+    let ul_background_alpha = context.get_uniform_location(&shader_program, "backgroundAlpha");
+
+    let ul_dithering = context.get_uniform_location(&shader_program, "dithering");
+    context.uniform1f(ul_dithering.as_ref(), if config.dithering { 1.0 } else { 0.0 });
+
+    let mut resources = FrameResources::new(&context, canvas.width() as i32, canvas.height() as i32)?;
+    bind_effect_state(&context, &shader_program, &resources.quad);
+    context.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+
+    let state = Rc::new(RefCell::new(State::new(current_time(), config.clone())));
+    state.borrow_mut().attach_canvas(canvas.clone());
+    let effect = Effect::new(state.clone(), Capabilities::new(context_id, highp_precision));
+    // The render loop's handle on the effect, for applying what `on_update`
+    // returns through the setters.
+    let mut frame_effect = Effect::new(state.clone(), effect.capabilities());
 
-    let start_time = get_current_time();
+    listen_to_pointer(&effect, config.pointer_target, &canvas, config.coalesce_pointer_events)?;
+
+    if config.capture_pointer {
+        capture_pointer_during_drags(&canvas)?;
+    }
+
+    {
+        // The ball is spawned at the pointer on the next frame.
+        let state = state.clone();
+        add_event_listener(&canvas, "mousedown", move |_| {
+            state.borrow_mut().spawn_requested = true;
+        })?;
+    }
+
+    for (event_name, hovered) in [("mouseenter", true), ("mouseleave", false)].iter() {
+        let state = state.clone();
+        let hovered = *hovered;
+        add_event_listener(&canvas, event_name, move |_| {
+            state.borrow_mut().hover_freeze.hovered = hovered;
+        })?;
+    }
+
+    let mut gpu_timer = GpuTimer::new(&context);
 
     start_animation(move || {
-        context.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+        let saved_gl_state = if state.borrow().preserve_gl_state {
+            let saved = GlState::capture(&context);
+            bind_effect_state(&context, &shader_program, &resources.quad);
+            Some(saved)
+        } else {
+            None
+        };
 
-        if let Some(ul_time) = &ul_time {
-            let current_time = get_current_time();
-            context.uniform1f(
-                Some(&ul_time),
-                (current_time - start_time) as f32
-            );
+        if let Some(gpu_timer) = &mut gpu_timer {
+            gpu_timer.begin_frame();
         }
 
-        if let Some(ul_mouse2) = &ul_mouse {
-            context.uniform2fv_with_f32_array(
-                Some(&ul_mouse2),
-                &vec![*mouse_x.borrow() as f32 / canvas_w as f32, *mouse_y.borrow() as f32 / canvas_h as f32]
+        let buffer_w = canvas.width() as f32;
+        let buffer_h = canvas.height() as f32;
+        let region = state.borrow().region(buffer_w, buffer_h);
+        state.borrow_mut().region_size = (region.width, region.height);
+        let (region_x, region_y) = region.gl_origin(buffer_h);
+
+        {
+            // mouse positions are in CSS pixels
+            let mut state = state.borrow_mut();
+            let pointer = state.pointer;
+            state.pointer_position = region.normalize(
+                pointer.x * buffer_w / canvas_w as f32,
+                pointer.y * buffer_h / canvas_h as f32
             );
         }
+        frame_effect.run_on_update();
+        let time = state.borrow_mut().update(current_time());
 
-        if let Some(ul_resolution) = &ul_resolution {
-            context.uniform2fv_with_f32_array(
-                Some(&ul_resolution),
-                &vec![canvas_w as f32, canvas_h as f32]
-            );
+        let blur_weights = gaussian_weights(state.borrow().blur_radius);
+        // The quad is kept across resizes; only the blur targets follow the
+        // drawing buffer.
+        if resources.resize(&context, buffer_w as i32, buffer_h as i32).is_err() {
+            error("Couldn't resize the blur framebuffers.");
+        }
+        if resources.set_blur(&context, !blur_weights.is_empty()).is_err() {
+            error("Couldn't create the blur framebuffers.");
+        }
+        if let Some((scene, _)) = &resources.blur_targets {
+            scene.bind(&context);
+        }
+
+        let (render_size, crisp_edges, filter) = {
+            let state = state.borrow();
+            (
+                render_size(region.width, region.height, state.resolution_scale, state.pixel_art),
+                state.crisp_edges && state.pixel_art.is_none(),
+                // pixel art keeps hard pixel edges when upscaled
+                if state.pixel_art.is_some() { WebGlRenderingContext::NEAREST } else { WebGlRenderingContext::LINEAR }
+            )
+        };
+        match render_size {
+            None => low_res_target = None,
+            Some((width, height)) => {
+                match &mut low_res_target {
+                    Some(target) => if target.resize(&context, width, height).is_err() {
+                        error("Couldn't resize the reduced resolution framebuffer.");
+                    },
+                    None => match RenderTarget::new(&context, width, height) {
+                        Ok(target) => low_res_target = Some(target),
+                        Err(_) => error("Couldn't create the reduced resolution framebuffer."),
+                    },
+                }
+                if let Some(target) = &mut low_res_target {
+                    target.set_filter(&context, filter);
+                }
+                if let Some((scene, _)) = &resources.blur_targets {
+                    // RenderTarget::new leaves the default framebuffer bound.
+                    scene.bind(&context);
+                }
+            },
+        }
+
+        let dabs = {
+            let mut state = state.borrow_mut();
+            let pointer = state.pointer;
+            if !state.paint.enabled {
+                None
+            } else {
+                if pointer.pressed {
+                    let (x, y) = state.pointer_position;
+                    state.paint.drag_to(x, y);
+                } else {
+                    state.paint.release();
+                }
+                let clear = state.paint.clear_requested;
+                state.paint.clear_requested = false;
+                Some((state.paint.take_dabs(), clear))
+            }
+        };
+        match dabs {
+            None => paint_target = None,
+            Some((dabs, clear)) => {
+                // Resizing reallocates the buffer, which loses the paint.
+                let ready = match &mut paint_target {
+                    Some(target) => target.resize(&context, buffer_w as i32, buffer_h as i32).is_ok(),
+                    None => match RenderTarget::new(&context, buffer_w as i32, buffer_h as i32) {
+                        Ok(target) => {
+                            paint_target = Some(target);
+                            true
+                        },
+                        Err(_) => false,
+                    },
+                };
+                match &paint_target {
+                    Some(target) if ready => {
+                        target.bind(&context);
+                        if clear {
+                            context.clear_color(0.0, 0.0, 0.0, 1.0);
+                            context.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+                        }
+                        context.use_program(Some(&dab_program));
+                        context.enable(WebGlRenderingContext::BLEND);
+                        context.blend_func_separate(WebGlRenderingContext::ONE, WebGlRenderingContext::ONE, WebGlRenderingContext::ZERO, WebGlRenderingContext::ONE);
+                        let radius = DAB_RADIUS * region.width.min(region.height);
+                        context.uniform1f(ul_dab_radius.as_ref(), radius);
+                        context.uniform1f(ul_dab_intensity.as_ref(), DAB_INTENSITY);
+                        for (x, y) in dabs {
+                            let (x, y) = (region.x + x * region.width, buffer_h - region.y - y * region.height);
+                            context.uniform2f(ul_dab_center.as_ref(), x, y);
+                            draw_quad(&context);
+                        }
+                        set_blend_func(&context);
+                        context.use_program(Some(&shader_program));
+                    },
+                    _ => error("Couldn't allocate the paint framebuffer."),
+                }
+                match &resources.blur_targets {
+                    Some((scene, _)) => scene.bind(&context),
+                    None => context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None),
+                }
+            },
+        }
+
+        let (frame_clear, palette) = {
+            let state = state.borrow();
+            (state.clear_strategy.frame(state.frame_dt as f32), state.palette())
+        };
+        let (background_r, background_g, background_b) = palette.background;
+        let scissor_rect = state.borrow().scissor_rect;
+        set_scissor(&context, scissor_rect, buffer_h);
+        context.viewport(0, 0, buffer_w as i32, buffer_h as i32);
+        if frame_clear.clear {
+            context.clear_color(background_r, background_g, background_b, 1.0);
+            context.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+        }
+        if frame_clear.fade_alpha > 0.0 {
+            context.use_program(Some(&fade_program));
+            context.uniform4f(ul_fade_color.as_ref(), background_r, background_g, background_b, frame_clear.fade_alpha);
+            context.enable(WebGlRenderingContext::BLEND);
+            draw_quad(&context);
+            context.use_program(Some(&shader_program));
+        }
+
+        // Layers below the blobs show through their background.
+        let draw_blobs = |over_layers: bool| {
+            let blend = frame_clear.blend || over_layers;
+            if blend {
+                context.enable(WebGlRenderingContext::BLEND);
+            } else {
+                context.disable(WebGlRenderingContext::BLEND);
+            }
+            context.uniform1f(ul_background_alpha.as_ref(), if blend { 0.0 } else { 1.0 });
+
+            // The scene is either drawn into the region or, at a reduced
+            // resolution, into the whole low resolution target.
+            let (render_region, render_buffer_h) = match &low_res_target {
+                Some(target) => {
+                    target.bind(&context);
+                    context.disable(WebGlRenderingContext::BLEND);
+                    // The target is redrawn whole and confined when upscaled.
+                    context.disable(WebGlRenderingContext::SCISSOR_TEST);
+                    (Region::new(0.0, 0.0, target.width as f32, target.height as f32), target.height as f32)
+                },
+                None => (region, buffer_h),
+            };
+            let (render_x, render_y) = render_region.gl_origin(render_buffer_h);
+            context.uniform1i(ul_field_pass.as_ref(), (low_res_target.is_some() && crisp_edges) as i32);
+
+            // The padding and the letterbox bars are left with whatever the
+            // clear strategy put there.
+            context.viewport(render_x as i32, render_y as i32, render_region.width as i32, render_region.height as i32);
+            if let Some(ul_time) = &ul_time {
+                context.uniform1f(
+                    Some(&ul_time),
+                    time as f32
+                );
+            }
+
+            if let Some(ul_mouse2) = &ul_mouse {
+                let ((x, y), grid_snap) = {
+                    let state = state.borrow();
+                    (state.pointer_position, state.grid_snap)
+                };
+                let (x, y) = match grid_snap {
+                    Some((cells_x, cells_y)) => snap_to_grid(x, y, cells_x, cells_y),
+                    None => (x, y),
+                };
+                context.uniform2fv_with_f32_array(Some(&ul_mouse2), &[x, y]);
+            }
+
+            if let Some(ul_resolution) = &ul_resolution {
+                context.uniform2fv_with_f32_array(
+                    Some(&ul_resolution),
+                    &[render_region.width, render_region.height]
+                );
+            }
+
+            context.uniform2fv_with_f32_array(ul_offset.as_ref(), &[render_x, render_y]);
+
+            let balls = state.borrow_mut().frame_balls();
+            context.uniform1i(ul_default_scene.as_ref(), balls.is_empty() as i32);
+            let upload_balls = |balls: &[Metaball]| {
+                context.uniform1i(ul_ball_count.as_ref(), balls.len().min(MAX_METABALLS) as i32);
+                if !balls.is_empty() {
+                    context.uniform3fv_with_f32_array(ul_balls.as_ref(), &metaball::to_uniform_array(balls));
+                    context.uniform4fv_with_f32_array(ul_shapes.as_ref(), &metaball::to_shape_uniform_array(balls));
+                }
+            };
+
+            {
+                let mut state = state.borrow_mut();
+                let params = state.ripples.params;
+                let ripples = state.ripples.update(time);
+                context.uniform4fv_with_f32_array(ul_ripples.as_ref(), &ripples);
+                context.uniform2fv_with_f32_array(ul_ripple_params.as_ref(), &[params.wavelength, params.speed]);
+                context.uniform1fv_with_f32_array(ul_params.as_ref(), &params_uniform(&state.params));
+                context.uniform1f(ul_cel_levels.as_ref(), state.cel_levels as f32);
+                context.uniform1f(ul_gamma.as_ref(), state.gamma);
+                context.uniform1f(ul_merge_smoothness.as_ref(), state.merge_smoothness);
+                context.uniform1i(ul_invert_field.as_ref(), state.invert_field as i32);
+                context.uniform1f(ul_opacity.as_ref(), state.opacity());
+                let ((r, g, b), strength) = state.pulse();
+                context.uniform4f(ul_pulse.as_ref(), r, g, b, strength);
+                let (r, g, b) = palette.edge;
+                context.uniform3f(ul_edge_color.as_ref(), r, g, b);
+                let (r, g, b) = palette.fill;
+                context.uniform3f(ul_fill_color.as_ref(), r, g, b);
+                context.uniform3f(ul_background_color.as_ref(), background_r, background_g, background_b);
+                context.uniform1f(ul_mouse_ball_scale.as_ref(), state.mouse_ball_scale());
+            }
+
+            let (tile_grid, cull_mode) = {
+                let state = state.borrow();
+                (state.tile_grid, state.cull_mode)
+            };
+            set_cull_mode(&context, cull_mode);
+            let render_scissor = if low_res_target.is_none() { scissor_rect } else { None };
+            match tile_grid {
+                Some((tiles_x, tiles_y)) if !balls.is_empty() => {
+                    // The scissor keeps gl_FragCoord and the uniforms of the whole region.
+                    context.enable(WebGlRenderingContext::SCISSOR_TEST);
+                    let mut bins = bin_balls(&balls, tiles_x, tiles_y, render_region.width, render_region.height, FIELD_REACH);
+                    let cut = limit_bins(&balls, &mut bins, tiles_x, tiles_y, render_region.width, render_region.height);
+                    {
+                        let mut state = state.borrow_mut();
+                        if cut > 0 && !state.tiles_overflowing {
+                            state.warn(&format!(
+                                "{} tile(s) are reached by more than {} metaballs; the farthest are left out there.",
+                                cut, MAX_METABALLS
+                            ));
+                        }
+                        state.tiles_overflowing = cut > 0;
+                    }
+                    for (index, bin) in bins.iter().enumerate() {
+                        let tile = tile_region(&render_region, tiles_x, tiles_y, index);
+                        let tile = render_scissor.map_or(tile, |rect| tile.intersect(&rect));
+                        let (tile_x, tile_y) = tile.gl_origin(render_buffer_h);
+                        context.scissor(
+                            tile_x.floor() as i32,
+                            tile_y.floor() as i32,
+                            (tile_x + tile.width).ceil() as i32 - tile_x.floor() as i32,
+                            (tile_y + tile.height).ceil() as i32 - tile_y.floor() as i32
+                        );
+                        let tile_balls: Vec<Metaball> = bin.iter().map(|&i| balls[i]).collect();
+                        upload_balls(&tile_balls);
+                        draw_quad(&context);
+                    }
+                    set_scissor(&context, render_scissor, render_buffer_h);
+                },
+                _ => {
+                    upload_balls(&balls);
+                    draw_quad(&context);
+                },
+            }
+            set_cull_mode(&context, CullMode::None);
+
+            if let Some(low_res_target) = &low_res_target {
+                match &resources.blur_targets {
+                    Some((scene, _)) => scene.bind(&context),
+                    None => context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None),
+                }
+                context.viewport(region_x as i32, region_y as i32, region.width as i32, region.height as i32);
+                set_scissor(&context, scissor_rect, buffer_h);
+                if blend {
+                    context.enable(WebGlRenderingContext::BLEND);
+                }
+                context.use_program(Some(&upscale_program));
+                context.uniform2f(ul_upscale_resolution.as_ref(), region.width, region.height);
+                context.uniform2f(ul_upscale_offset.as_ref(), region_x, region_y);
+                context.uniform1i(ul_upscale_crisp.as_ref(), crisp_edges as i32);
+                context.uniform1f(ul_upscale_edge_width.as_ref(), edge_width(region.width.min(region.height)));
+                context.uniform1f(ul_upscale_background_alpha.as_ref(), if blend { 0.0 } else { 1.0 });
+                context.uniform3f(ul_upscale_background_color.as_ref(), background_r, background_g, background_b);
+                context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&low_res_target.texture));
+                draw_quad(&context);
+                context.use_program(Some(&shader_program));
+            }
+        };
+
+        let draw_paint = || {
+            if let Some(paint_target) = &paint_target {
+                context.viewport(0, 0, buffer_w as i32, buffer_h as i32);
+                context.enable(WebGlRenderingContext::BLEND);
+                context.blend_func_separate(WebGlRenderingContext::ONE, WebGlRenderingContext::ONE, WebGlRenderingContext::ZERO, WebGlRenderingContext::ONE);
+                context.use_program(Some(&upscale_program));
+                context.uniform2f(ul_upscale_resolution.as_ref(), buffer_w, buffer_h);
+                context.uniform2f(ul_upscale_offset.as_ref(), 0.0, 0.0);
+                context.uniform1i(ul_upscale_crisp.as_ref(), 0);
+                context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&paint_target.texture));
+                draw_quad(&context);
+                set_blend_func(&context);
+                context.use_program(Some(&shader_program));
+            }
+            paint_target.is_some()
+        };
+
+        let layer_order = state.borrow().layer_order.clone();
+        let mut drawn_below = false;
+        for layer in &layer_order {
+            match layer {
+                Layer::Blobs => draw_blobs(drawn_below),
+                Layer::Paint => drawn_below |= draw_paint(),
+            }
         }
 
-        context.draw_elements_with_i32(WebGlRenderingContext::TRIANGLES, 6, WebGlRenderingContext::UNSIGNED_SHORT, 0);
+        if resources.is_blurring() {
+            blur_pass.draw(&context, &resources, &blur_weights);
+            context.use_program(Some(&shader_program));
+        }
+        if let Some(gpu_timer) = &mut gpu_timer {
+            gpu_timer.end_frame();
+            if let Some(seconds) = gpu_timer.poll() {
+                state.borrow_mut().frame_stats.record_gpu_time(seconds);
+            }
+        }
+        // The offscreen targets of the next frame aren't confined.
+        context.disable(WebGlRenderingContext::SCISSOR_TEST);
+        if let Some(saved_gl_state) = saved_gl_state {
+            saved_gl_state.restore(&context);
+        }
         context.flush();
+
+        let mirror_ids = state.borrow().mirror_ids.clone();
+        if !mirror_ids.is_empty() {
+            // Mirrors removed from the page since are skipped.
+            let mirrors: Vec<web_sys::HtmlCanvasElement> = mirror_ids.iter()
+                .filter_map(|id| get_canvas_element_by_id(id).ok())
+                .collect();
+            let _ = mirror_canvas(&canvas, &mirrors);
+        }
     });
 
+    Ok(effect)
+}
+
+/// Copies the drawing buffer of `source` to `targets`, scaled to fill each
+/// of them, and returns how many were drawn to.
+pub fn mirror_canvas(source: &web_sys::HtmlCanvasElement, targets: &[web_sys::HtmlCanvasElement]) -> Result<usize, JsValue> {
+    for target in targets {
+        let context = target.get_context("2d")?
+            .ok_or(JsValue::from("Couldn't get a 2D context for a mirror."))?
+            .dyn_into::<web_sys::CanvasRenderingContext2d>()?;
+        let (width, height) = (target.width() as f64, target.height() as f64);
+        context.clear_rect(0.0, 0.0, width, height);
+        context.draw_image_with_html_canvas_element_and_dw_and_dh(source, 0.0, 0.0, width, height)?;
+    }
+    Ok(targets.len())
+}
+
+/// Side of the canvas the self-test renders to, in pixels.
+const SELF_TEST_SIZE: u32 = 16;
+
+/// Renders a single centered ball to an offscreen canvas with the scene
+/// program and reads back its center and a corner, which should be the lit
+/// fill and the black background. The error names the check that failed.
+pub(crate) fn run_self_test(context_ids: &[String]) -> Result<(), JsValue> {
+    let fail = |check: &str, detail: String| Err(JsValue::from(&format!("Self-test failed at {}: {}", check, detail)));
+
+    let canvas = window().document().ok_or(JsValue::from("no `document` exists"))?
+        .create_element("canvas")?
+        .dyn_into::<web_sys::HtmlCanvasElement>()?;
+    canvas.set_width(SELF_TEST_SIZE);
+    canvas.set_height(SELF_TEST_SIZE);
+    let context = match get_webgl_context(&canvas, context_ids) {
+        Ok((context, _)) => context,
+        Err(error) => return fail("context creation", error.as_string().unwrap_or_default()),
+    };
+    let shader_program = match init_shaders(&context) {
+        Ok(program) => program,
+        Err(error) => return fail("shader compilation", error.as_string().unwrap_or_default()),
+    };
+    let quad = QuadBuffers::new(&context)?;
+    bind_effect_state(&context, &shader_program, &quad);
+
+    let size = SELF_TEST_SIZE as f32;
+    let uniform = |name: &str| context.get_uniform_location(&shader_program, name);
+    context.uniform2f(uniform("resolution").as_ref(), size, size);
+    context.uniform2f(uniform("offset").as_ref(), 0.0, 0.0);
+    context.uniform1i(uniform("ballCount").as_ref(), 1);
+    context.uniform3f(uniform("balls").as_ref(), 0.5, 0.5, 0.25);
+    context.uniform4f(uniform("shapes").as_ref(), 0.0, 0.0, 0.0, 0.0);
+    context.uniform1f(uniform("gamma").as_ref(), 1.0);
+    context.uniform1f(uniform("opacity").as_ref(), 1.0);
+    context.uniform1f(uniform("mouseBallScale").as_ref(), 1.0);
+    context.uniform1f(uniform("backgroundAlpha").as_ref(), 1.0);
+    context.uniform3f(uniform("fillColor").as_ref(), 1.0, 1.0, 1.0);
+    context.uniform3f(uniform("edgeColor").as_ref(), 1.0, 1.0, 1.0);
+    context.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+    draw_quad(&context);
+    let error = context.get_error();
+    if error != WebGlRenderingContext::NO_ERROR {
+        return fail("drawing", format!("GL error {:#x}", error));
+    }
+
+    let pixel = |x: u32, y: u32| -> Result<[u8; 4], JsValue> {
+        let mut pixel = [0; 4];
+        context.read_pixels_with_opt_u8_array(
+            x as i32, y as i32, 1, 1,
+            WebGlRenderingContext::RGBA, WebGlRenderingContext::UNSIGNED_BYTE,
+            Some(&mut pixel)
+        )?;
+        Ok(pixel)
+    };
+    let center = pixel(SELF_TEST_SIZE / 2, SELF_TEST_SIZE / 2)?;
+    if center[..3].iter().any(|&channel| channel < 64) {
+        return fail("the ball's center", format!("expected a lit fill, read {:?}", center));
+    }
+    let corner = pixel(0, 0)?;
+    if corner[..3].iter().any(|&channel| channel > 16) {
+        return fail("the background", format!("expected black, read {:?}", corner));
+    }
     Ok(())
 }
 
+/// The separable Gaussian blur run over the scene at the end of a frame.
+pub struct BlurPass {
+    program: WebGlProgram,
+    resolution: Option<WebGlUniformLocation>,
+    direction: Option<WebGlUniformLocation>,
+    weights: Option<WebGlUniformLocation>,
+    taps: Option<WebGlUniformLocation>,
+}
+
+impl BlurPass {
+    pub fn new(context: &WebGlRenderingContext) -> Result<BlurPass, JsValue> {
+        let program = init_program(context, &format!("#define MAX_BLUR_TAPS {}\n{}", MAX_BLUR_TAPS, BLUR_FRAGMENT_SHADER))?;
+        let uniform = |name: &str| context.get_uniform_location(&program, name);
+        Ok(BlurPass {
+            resolution: uniform("resolution"),
+            direction: uniform("direction"),
+            weights: uniform("weights"),
+            taps: uniform("taps"),
+            program,
+        })
+    }
+
+    /// Blurs the scene drawn into the blur targets of `resources` with
+    /// `weights` from `gaussian_weights`, horizontally into the second target
+    /// and then vertically onto the canvas. The quad must be bound. It leaves
+    /// the blur program in use and blending disabled.
+    pub fn draw(&self, context: &WebGlRenderingContext, resources: &FrameResources, weights: &[f32]) {
+        let Some((scene, pass)) = &resources.blur_targets else {
+            return;
+        };
+        let (width, height) = resources.size();
+        context.disable(WebGlRenderingContext::BLEND);
+        context.use_program(Some(&self.program));
+        context.uniform1fv_with_f32_array(self.weights.as_ref(), weights);
+        context.uniform1i(self.taps.as_ref(), weights.len() as i32);
+        context.uniform2f(self.resolution.as_ref(), width as f32, height as f32);
+
+        pass.bind(context);
+        context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&scene.texture));
+        context.uniform2f(self.direction.as_ref(), 1.0, 0.0);
+        draw_quad(context);
+
+        context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+        context.viewport(0, 0, width, height);
+        context.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&pass.texture));
+        context.uniform2f(self.direction.as_ref(), 0.0, 1.0);
+        draw_quad(context);
+    }
+}
+
+/// Sets up the state every frame assumes: the scene program, the quad's
+/// vertex setup, the clear color and the blend function.
+fn bind_effect_state(context: &WebGlRenderingContext, shader_program: &WebGlProgram, quad: &QuadBuffers) {
+    context.use_program(Some(shader_program));
+    bind_quad(context, quad);
+
+    context.clear_color(0.0, 0.0, 0.0, 1.0);
+    set_blend_func(context);
+}
+
+/// Feeds `quad` to the `position` attribute of the programs `init_program`
+/// links, for `draw_quad`.
+pub fn bind_quad(context: &WebGlRenderingContext, quad: &QuadBuffers) {
+    context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&quad.position));
+    context.enable_vertex_attrib_array(POSITION_LOCATION);
+    context.vertex_attrib_pointer_with_i32(
+        POSITION_LOCATION,
+        3,
+        WebGlRenderingContext::FLOAT,
+        false,
+        0,
+        0
+    );
+    context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&quad.index));
+}
+
+/// The blend function of the scene. Only the color is blended, so the canvas
+/// stays opaque.
+fn set_blend_func(context: &WebGlRenderingContext) {
+    context.blend_func_separate(
+        WebGlRenderingContext::SRC_ALPHA,
+        WebGlRenderingContext::ONE_MINUS_SRC_ALPHA,
+        WebGlRenderingContext::ZERO,
+        WebGlRenderingContext::ONE
+    );
+}
+
+/// Confines clears and draws to `rect`, in drawing buffer pixels from the
+/// top-left corner of a buffer `buffer_height` pixels high, or lifts the
+/// confinement without one.
+pub fn set_scissor(context: &WebGlRenderingContext, rect: Option<Region>, buffer_height: f32) {
+    match rect {
+        Some(rect) => {
+            let (x, y) = rect.gl_origin(buffer_height);
+            context.enable(WebGlRenderingContext::SCISSOR_TEST);
+            context.scissor(
+                x.floor() as i32,
+                y.floor() as i32,
+                (x + rect.width).ceil() as i32 - x.floor() as i32,
+                (y + rect.height).ceil() as i32 - y.floor() as i32
+            );
+        },
+        None => context.disable(WebGlRenderingContext::SCISSOR_TEST),
+    }
+}
+
+/// Culls the faces `mode` selects, the quad being wound counter-clockwise.
+pub fn set_cull_mode(context: &WebGlRenderingContext, mode: CullMode) {
+    match mode {
+        CullMode::None => context.disable(WebGlRenderingContext::CULL_FACE),
+        CullMode::Back | CullMode::Front => {
+            context.enable(WebGlRenderingContext::CULL_FACE);
+            context.front_face(WebGlRenderingContext::CCW);
+            context.cull_face(if mode == CullMode::Back { WebGlRenderingContext::BACK } else { WebGlRenderingContext::FRONT });
+        },
+    }
+}
+
+pub fn draw_quad(context: &WebGlRenderingContext) {
+    context.draw_elements_with_i32(WebGlRenderingContext::TRIANGLES, 6, WebGlRenderingContext::UNSIGNED_SHORT, 0);
+}
+
 fn get_canvas_element_by_id(id: &str) -> Result<web_sys::HtmlCanvasElement, JsValue> {
     let document = web_sys::window()
         .unwrap()
@@ -188,15 +1063,30 @@ fn get_canvas_element_by_id(id: &str) -> Result<web_sys::HtmlCanvasElement, JsVa
         .or_else(|e| Err(JsValue::from(e)))
 }
 
-fn get_webgl_context(canvas: &web_sys::HtmlCanvasElement) -> Result<WebGlRenderingContext, JsValue> {
-    let context = canvas
-        .get_context("webgl")?
-        .ok_or(JsValue::from("Couldn't get WebGL context.2"))?
-        .dyn_into::<WebGlRenderingContext>()?;
+fn get_webgl_context(canvas: &web_sys::HtmlCanvasElement, context_ids: &[String]) -> Result<(WebGlRenderingContext, String), JsValue> {
+    // Clear strategies other than a full clear draw over the previous frame.
+    let attributes = web_sys::WebGlContextAttributes::new();
+    attributes.set_preserve_drawing_buffer(true);
+
+    let (context_id, context) = first_available_context(context_ids, |id| {
+        canvas.get_context_with_context_options(id, &attributes).ok().flatten()
+    }).ok_or(JsValue::from(&format!("Couldn't get WebGL context: {}", context_ids.join(", "))))?;
 
+    // WebGL2RenderingContext isn't a subclass of WebGLRenderingContext, but
+    // provides everything used from it.
+    let context = context.unchecked_into::<WebGlRenderingContext>();
     context.viewport(0, 0, canvas.width() as i32, canvas.height() as i32);
 
-    Ok(context)
+    Ok((context, context_id))
+}
+
+/// Returns the first identifier `get_context` succeeds with, along with its context.
+pub fn first_available_context<T, F>(context_ids: &[String], mut get_context: F) -> Option<(String, T)>
+where
+    F: FnMut(&str) -> Option<T>
+{
+    context_ids.iter()
+        .find_map(|id| get_context(id).map(|context| (id.clone(), context)))
 }
 
 fn get_shader(context: &WebGlRenderingContext, shader_type: u32, source: &str) -> Result<WebGlShader, JsValue> {
@@ -213,12 +1103,26 @@ fn get_shader(context: &WebGlRenderingContext, shader_type: u32, source: &str) -
 }
 
 fn init_shaders(context: &WebGlRenderingContext) -> Result<WebGlProgram, JsValue> {
-    let fragment_shader = get_shader(&context, WebGlRenderingContext::FRAGMENT_SHADER, FRAGMENT_SHADER)?;
+    let shader_program = init_program(context, &fragment_shader_source())?;
+
+    context.use_program(Some(&shader_program));
+
+    Ok(shader_program)
+}
+
+/// Location `position` is bound to in every program.
+const POSITION_LOCATION: u32 = 0;
+
+/// Links `fragment_source` with the quad vertex shader. `position` is bound to
+/// the same location in every program, so they share the vertex setup.
+pub fn init_program(context: &WebGlRenderingContext, fragment_source: &str) -> Result<WebGlProgram, JsValue> {
+    let fragment_shader = get_shader(&context, WebGlRenderingContext::FRAGMENT_SHADER, fragment_source)?;
     let vertex_shader = get_shader(&context, WebGlRenderingContext::VERTEX_SHADER, VERTEX_SHADER)?;
 
     let shader_program = context.create_program().unwrap();
     context.attach_shader(&shader_program, &vertex_shader);
     context.attach_shader(&shader_program, &fragment_shader);
+    context.bind_attrib_location(&shader_program, POSITION_LOCATION, "position");
     context.link_program(&shader_program);
 
     let shader_is_created = context.get_program_parameter(&shader_program, WebGlRenderingContext::LINK_STATUS).as_bool().unwrap();
@@ -228,46 +1132,9 @@ fn init_shaders(context: &WebGlRenderingContext) -> Result<WebGlProgram, JsValue
         return Err(JsValue::from(&format!("シェーダープログラムを初期化できません: {}", info)))
     }
 
-    context.use_program(Some(&shader_program));
-
     Ok(shader_program)
 }
 
-fn init_buffers(context: &WebGlRenderingContext) -> (WebGlBuffer, WebGlBuffer) {
-    let position = [
-        -1.0,  1.0, 0.0,
-         1.0,  1.0, 0.0,
-        -1.0, -1.0, 0.0,
-         1.0, -1.0, 0.0
-    ];
-    let position_buffer = context.create_buffer().unwrap();
-    context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&position_buffer));
-    unsafe {
-        context.buffer_data_with_array_buffer_view(
-            WebGlRenderingContext::ARRAY_BUFFER,
-            &js_sys::Float32Array::view(&position),
-            WebGlRenderingContext::STATIC_DRAW
-        );
-    }
-
-
-    let index = [
-        0, 2, 1,
-        1, 2, 3
-    ];
-    let index_buffer = context.create_buffer().unwrap();
-    context.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&index_buffer));
-    unsafe {
-        context.buffer_data_with_array_buffer_view(
-            WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
-            &js_sys::Uint16Array::view(&index),
-            WebGlRenderingContext::STATIC_DRAW
-        );
-    }
-
-    (position_buffer, index_buffer)
-}
-
 // fn format_as_matrix<T: std::fmt::Display>(vec: Vec<T>, len_row: usize, len_column: usize) -> String {
 //     let len = vec.len();
 //     if len != len_column * len_row {
@@ -281,10 +1148,6 @@ fn init_buffers(context: &WebGlRenderingContext) -> (WebGlBuffer, WebGlBuffer) {
 //     }).collect::<Vec<_>>().join("\n")
 // }
 
-fn get_current_time() -> f64 { // sec
-    js_sys::Date::now() / 1000.0
-}
-
 fn window() -> web_sys::Window {
     web_sys::window().expect("no global `window` exists")
 }
@@ -295,7 +1158,128 @@ fn request_animation_frame(f: &Closure<dyn FnMut()>) {
         .expect("should register `requestAnimationFrame` OK");
 }
 
-fn add_event_listener<T>(target: &web_sys::Element, event_name: &str, handler: T) -> Result<(), JsValue>
+pub fn pointer_event_target(target: PointerTarget, canvas: &web_sys::HtmlCanvasElement) -> web_sys::EventTarget {
+    match target {
+        PointerTarget::Canvas => canvas.clone().into(),
+        PointerTarget::Document => window().document().expect("no `document` exists").into(),
+        PointerTarget::Window => window().into(),
+    }
+}
+
+/// Feeds the pointer positions and buttons of the events `target` receives to
+/// `effect`, relative to `canvas`. Releases are taken from the whole window,
+/// so a drag ending outside `target` doesn't leave the button held.
+pub fn listen_to_pointer(effect: &Effect, target: PointerTarget, canvas: &web_sys::HtmlCanvasElement, coalesce: bool) -> Result<(), JsValue> {
+    let target = pointer_event_target(target, canvas);
+    let (down, up) = if coalesce { ("pointerdown", "pointerup") } else { ("mousedown", "mouseup") };
+    for (event_target, event_name) in [(target.clone(), down), (window().into(), up)].iter() {
+        let state = effect.shared_state();
+        add_event_listener(event_target, event_name, move |event| {
+            state.borrow_mut().pointer.pressed = primary_button_held(&event);
+        })?;
+    }
+
+    let state = effect.shared_state();
+    let canvas = canvas.clone();
+    let event_name = if coalesce { "pointermove" } else { "mousemove" };
+    add_event_listener(&target, event_name, move |event| {
+        let pressed = primary_button_held(&event);
+        let samples = if coalesce { coalesced_events(&event) } else { vec![] };
+        let samples = if samples.is_empty() { vec![event] } else { samples };
+
+        // offsetX/Y are relative to the event target, which isn't necessarily the canvas.
+        let rect = canvas.get_bounding_client_rect();
+        let samples: Vec<(f32, f32, f64)> = samples.iter()
+            .filter_map(|event| event.dyn_ref::<web_sys::MouseEvent>())
+            .map(|event| (
+                (event.client_x() as f64 - rect.left()) as f32,
+                (event.client_y() as f64 - rect.top()) as f32,
+                clock::test_time().unwrap_or(event.time_stamp() / 1000.0)
+            ))
+            .collect();
+        let mut state = state.borrow_mut();
+        state.pointer.push_all(&samples);
+        state.pointer.pressed = pressed;
+    })
+}
+
+/// Feeds the keys pressed and the wheel scrolled on the focused `canvas` to
+/// `state`, keeping the input it uses from the page, e.g. Space from
+/// scrolling it.
+pub(crate) fn listen_to_focused_input(state: &Rc<RefCell<State>>, canvas: &web_sys::HtmlCanvasElement) -> Result<(), JsValue> {
+    {
+        let state = state.clone();
+        add_event_listener(canvas, "keydown", move |event| {
+            let used = event.dyn_ref::<web_sys::KeyboardEvent>()
+                .is_some_and(|event| state.borrow_mut().key_down(&event.key()));
+            if used {
+                event.prevent_default();
+            }
+        })?;
+    }
+
+    {
+        let state = state.clone();
+        add_event_listener(canvas, "wheel", move |event| {
+            let used = event.dyn_ref::<web_sys::WheelEvent>()
+                .is_some_and(|event| state.borrow_mut().wheel(event.delta_y() as f32));
+            if used {
+                event.prevent_default();
+            }
+        })?;
+    }
+
+    for (event_name, focused) in [("focus", true), ("blur", false)].iter() {
+        let state = state.clone();
+        let focused = *focused;
+        add_event_listener(canvas, event_name, move |_| {
+            state.borrow_mut().focused = focused;
+        })?;
+    }
+    Ok(())
+}
+
+fn primary_button_held(event: &web_sys::Event) -> bool {
+    event.dyn_ref::<web_sys::MouseEvent>()
+        .is_some_and(|event| event.buttons() & 1 != 0)
+}
+
+/// Captures the pointer pressed on `canvas` until it's released, so its
+/// move events keep arriving while it's dragged outside. The positions are
+/// then outside the canvas, beyond 0 to 1 once normalized.
+pub fn capture_pointer_during_drags(canvas: &web_sys::HtmlCanvasElement) -> Result<(), JsValue> {
+    for (event_name, capture) in [("pointerdown", true), ("pointerup", false)].iter() {
+        let canvas_target = canvas.clone();
+        let capture = *capture;
+        add_event_listener(canvas, event_name, move |event| {
+            if let Some(event) = event.dyn_ref::<web_sys::PointerEvent>() {
+                // fails for pointers that aren't pressed, which needs no capture
+                let _ = if capture {
+                    canvas_target.set_pointer_capture(event.pointer_id())
+                } else {
+                    canvas_target.release_pointer_capture(event.pointer_id())
+                };
+            }
+        })?;
+    }
+    Ok(())
+}
+
+/// The events coalesced into a pointermove event, or none where
+/// getCoalescedEvents isn't supported.
+fn coalesced_events(event: &web_sys::Event) -> Vec<web_sys::Event> {
+    let supported = js_sys::Reflect::get(event, &JsValue::from("getCoalescedEvents"))
+        .is_ok_and(|method| method.is_function());
+    match event.dyn_ref::<web_sys::PointerEvent>() {
+        Some(pointer_event) if supported => pointer_event.get_coalesced_events()
+            .iter()
+            .filter_map(|event| event.dyn_into::<web_sys::Event>().ok())
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn add_event_listener<T>(target: &web_sys::EventTarget, event_name: &str, handler: T) -> Result<(), JsValue>
 where
     T: 'static + FnMut(web_sys::Event)
 {
@@ -0,0 +1,199 @@
+use wasm_bindgen::prelude::*;
+
+use crate::easing::ease_in_out;
+
+/// Number of balls the fragment shader has uniform slots for.
+pub const MAX_METABALLS: usize = 16;
+
+/// Selects the shape passed to `add_metaball_shape`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShapeKind {
+    Circle,
+    Ellipse,
+    Capsule,
+}
+
+/// The outline a ball's field is measured from. Lengths are fractions of the
+/// shorter canvas side, like the radius.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    Circle,
+    /// An axis-aligned ellipse with its own radii, which replace the radius.
+    Ellipse { rx: f32, ry: f32 },
+    /// A segment of `length` through the center, rotated counterclockwise by
+    /// `angle` radians and thickened by the radius.
+    Capsule { length: f32, angle: f32 },
+}
+
+impl Shape {
+    /// The shape of `kind` with parameters `a` and `b`: the radii of an
+    /// ellipse, or the length and angle of a capsule.
+    pub fn from_kind(kind: ShapeKind, a: f32, b: f32) -> Shape {
+        match kind {
+            ShapeKind::Circle => Shape::Circle,
+            ShapeKind::Ellipse => Shape::Ellipse { rx: a, ry: b },
+            ShapeKind::Capsule => Shape::Capsule { length: a, angle: b },
+        }
+    }
+
+    pub fn kind(&self) -> ShapeKind {
+        match self {
+            Shape::Circle => ShapeKind::Circle,
+            Shape::Ellipse { .. } => ShapeKind::Ellipse,
+            Shape::Capsule { .. } => ShapeKind::Capsule,
+        }
+    }
+
+    /// The parameters `from_kind` takes.
+    pub fn params(&self) -> (f32, f32) {
+        match *self {
+            Shape::Circle => (0.0, 0.0),
+            Shape::Ellipse { rx, ry } => (rx, ry),
+            Shape::Capsule { length, angle } => (length, angle),
+        }
+    }
+}
+
+/// A ball in normalized canvas coordinates, with the origin at the top-left
+/// corner. `radius` is a fraction of the shorter canvas side, and the
+/// velocity is in normalized coordinates per second.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Metaball {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    pub vx: f32,
+    pub vy: f32,
+    pub shape: Shape,
+    pub lifetime: Option<Lifetime>,
+    /// Spawned by an emitter, which spawns it anew when the effect is
+    /// replayed.
+    pub emitted: bool,
+}
+
+/// Fraction of a lifetime at the end over which a ball fades out.
+pub const LIFETIME_FADE: f32 = 0.25;
+
+/// How long a ball has left before it's removed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lifetime {
+    pub total: f32,
+    pub remaining: f32,
+}
+
+impl Lifetime {
+    pub fn new(seconds: f32) -> Lifetime {
+        Lifetime { total: seconds, remaining: seconds }
+    }
+
+    /// Counts down by `dt` seconds and returns whether the ball is still
+    /// alive.
+    pub fn tick(&mut self, dt: f32) -> bool {
+        self.remaining -= dt;
+        self.remaining > 0.0
+    }
+
+    /// The factor the radius is scaled by: 1 until the last `LIFETIME_FADE`
+    /// of the lifetime, then easing out to 0.
+    pub fn fade(&self) -> f32 {
+        let fade = self.total * LIFETIME_FADE;
+        if fade <= 0.0 {
+            return 1.0;
+        }
+        ease_in_out(self.remaining / fade)
+    }
+}
+
+impl Metaball {
+    pub fn new(x: f32, y: f32, radius: f32) -> Metaball {
+        Metaball::with_shape(x, y, radius, Shape::Circle)
+    }
+
+    pub fn with_shape(x: f32, y: f32, radius: f32, shape: Shape) -> Metaball {
+        Metaball { x, y, radius, vx: 0.0, vy: 0.0, shape, lifetime: None, emitted: false }
+    }
+
+    /// The distance from the center the outline reaches at most.
+    pub fn extent(&self) -> f32 {
+        match self.shape {
+            Shape::Circle => self.radius,
+            Shape::Ellipse { rx, ry } => rx.max(ry),
+            Shape::Capsule { length, .. } => self.radius + length / 2.0,
+        }
+    }
+}
+
+/// Checks that `requested` more balls fit next to the `current` ones, with
+/// `capacity` being `MAX_METABALLS` or, when rendering in tiles,
+/// `MAX_TILED_METABALLS`.
+pub fn check_capacity(current: usize, requested: usize, capacity: usize) -> Result<(), String> {
+    if current + requested > capacity {
+        return Err(format!(
+            "Can't add {} metaball(s): {} of {} are already in use.",
+            requested, current, capacity
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that a ball at (`x`, `y`) with `radius` and `shape` can be
+/// evaluated: a finite position, and a positive radius, or positive radii
+/// for an ellipse. Other values would turn the field into NaN or infinity.
+pub fn check_ball(x: f32, y: f32, radius: f32, shape: Shape) -> Result<(), String> {
+    if !x.is_finite() || !y.is_finite() {
+        return Err(format!("Can't add a metaball at ({}, {}): the position must be finite.", x, y));
+    }
+    let valid_length = |length: f32| length.is_finite() && length > 0.0;
+    match shape {
+        Shape::Ellipse { rx, ry } if !valid_length(rx) || !valid_length(ry) => {
+            Err(format!("Can't add an ellipse with radii {} and {}: they must be positive.", rx, ry))
+        },
+        Shape::Capsule { length, angle } if !length.is_finite() || length < 0.0 || !angle.is_finite() => {
+            Err(format!("Can't add a capsule of length {} at {} radians.", length, angle))
+        },
+        Shape::Ellipse { .. } => Ok(()),
+        _ if !valid_length(radius) => Err(format!("Can't add a metaball of radius {}: it must be positive.", radius)),
+        _ => Ok(()),
+    }
+}
+
+/// `radius` raised so that it spans at least `min_screen_radius` pixels on a
+/// region whose shorter side is `shorter_side` pixels.
+pub fn effective_radius(radius: f32, min_screen_radius: f32, shorter_side: f32) -> f32 {
+    if shorter_side <= 0.0 {
+        return radius;
+    }
+    radius.max(min_screen_radius / shorter_side)
+}
+
+/// The factor a ball's radius is grown by when melting near the cursor:
+/// 1 + `strength` at no `distance`, easing back to 1 at `reach`. Both
+/// lengths are fractions of the shorter canvas side.
+pub fn melt_factor(distance: f32, strength: f32, reach: f32) -> f32 {
+    if reach <= 0.0 {
+        return 1.0;
+    }
+    let t = (1.0 - distance / reach).clamp(0.0, 1.0);
+    1.0 + strength * t * t
+}
+
+/// Flattens the balls into the `vec3 balls[MAX_METABALLS]` uniform layout.
+pub fn to_uniform_array(balls: &[Metaball]) -> Vec<f32> {
+    balls.iter()
+        .take(MAX_METABALLS)
+        .flat_map(|ball| vec![ball.x, ball.y, ball.radius])
+        .collect()
+}
+
+/// Flattens the shapes into the `vec4 shapes[MAX_METABALLS]` uniform layout:
+/// the `ShapeKind` followed by the shape's parameters.
+pub fn to_shape_uniform_array(balls: &[Metaball]) -> Vec<f32> {
+    balls.iter()
+        .take(MAX_METABALLS)
+        .flat_map(|ball| {
+            let (a, b) = ball.shape.params();
+            vec![ball.shape.kind() as u32 as f32, a, b, 0.0]
+        })
+        .collect()
+}
@@ -0,0 +1,36 @@
+use wasm_bindgen::prelude::*;
+
+/// How positions passed to the effect are interpreted.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Origin {
+    /// Normalized canvas coordinates, (0, 0) at the top-left and (1, 1) at
+    /// the bottom-right corner.
+    TopLeft,
+    /// (0, 0) at the center with y pointing up and the shorter canvas side
+    /// spanning -1 to 1, like the shader's fragment position.
+    Center,
+}
+
+/// Converts a position in `origin` coordinates of a `width` x `height`
+/// region to normalized top-left coordinates.
+pub fn to_normalized(origin: Origin, x: f32, y: f32, width: f32, height: f32) -> (f32, f32) {
+    match origin {
+        Origin::TopLeft => (x, y),
+        Origin::Center => {
+            let shorter = width.min(height);
+            (0.5 + x * shorter / (2.0 * width), 0.5 - y * shorter / (2.0 * height))
+        },
+    }
+}
+
+/// The inverse of `to_normalized`.
+pub fn from_normalized(origin: Origin, x: f32, y: f32, width: f32, height: f32) -> (f32, f32) {
+    match origin {
+        Origin::TopLeft => (x, y),
+        Origin::Center => {
+            let shorter = width.min(height);
+            ((x - 0.5) * 2.0 * width / shorter, (0.5 - y) * 2.0 * height / shorter)
+        },
+    }
+}
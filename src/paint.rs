@@ -0,0 +1,68 @@
+/// Radius of the dabs a paint stroke is made of, as a fraction of the shorter
+/// side of the region.
+pub const DAB_RADIUS: f32 = 0.03;
+
+/// Intensity a dab adds at its center.
+pub const DAB_INTENSITY: f32 = 0.05;
+
+/// Distance between the dabs along a stroke, in normalized coordinates.
+const DAB_SPACING: f32 = DAB_RADIUS / 4.0;
+
+/// The intensity the dab at `dab` adds at (`x`, `y`), in normalized
+/// coordinates of a `width` x `height` region. Matches the dab shader.
+pub fn dab_value(dab: (f32, f32), x: f32, y: f32, width: f32, height: f32) -> f32 {
+    let radius = DAB_RADIUS * width.min(height);
+    let distance = ((x - dab.0) * width).hypot((y - dab.1) * height) / radius;
+    DAB_INTENSITY * (-distance * distance * 2.0).exp()
+}
+
+/// Turns pointer drags into dabs for the accumulation buffer.
+#[derive(Default)]
+pub struct Paint {
+    pub enabled: bool,
+    /// Set by `clear_paint` until the buffer has been cleared.
+    pub clear_requested: bool,
+    pending: Vec<(f32, f32)>,
+    last: Option<(f32, f32)>,
+}
+
+impl Paint {
+    pub fn new() -> Paint {
+        Paint::default()
+    }
+
+    /// Continues the stroke to a position in normalized coordinates, evenly
+    /// spacing dabs along the way.
+    pub fn drag_to(&mut self, x: f32, y: f32) {
+        let (mut last_x, mut last_y) = match self.last {
+            Some(last) => last,
+            None => {
+                self.pending.push((x, y));
+                self.last = Some((x, y));
+                return;
+            },
+        };
+        let distance = (x - last_x).hypot(y - last_y);
+        let steps = (distance / DAB_SPACING).floor() as usize;
+        if steps == 0 {
+            return;
+        }
+        let (step_x, step_y) = ((x - last_x) / distance * DAB_SPACING, (y - last_y) / distance * DAB_SPACING);
+        for _ in 0..steps {
+            last_x += step_x;
+            last_y += step_y;
+            self.pending.push((last_x, last_y));
+        }
+        self.last = Some((last_x, last_y));
+    }
+
+    /// Ends the stroke, so the next drag doesn't connect to it.
+    pub fn release(&mut self) {
+        self.last = None;
+    }
+
+    /// The dabs deposited since the last call.
+    pub fn take_dabs(&mut self) -> Vec<(f32, f32)> {
+        std::mem::take(&mut self.pending)
+    }
+}
@@ -0,0 +1,10 @@
+/// Length of the `params` uniform array.
+pub const MAX_PARAMS: usize = 16;
+
+/// `values` as uploaded to `params`: padded with zeros, or truncated, to
+/// `MAX_PARAMS` entries.
+pub fn params_uniform(values: &[f32]) -> Vec<f32> {
+    let mut uniform: Vec<f32> = values.iter().cloned().take(MAX_PARAMS).collect();
+    uniform.resize(MAX_PARAMS, 0.0);
+    uniform
+}
@@ -0,0 +1,92 @@
+use crate::metaball::Metaball;
+
+/// Moves the balls by their velocities over `dt` seconds, bouncing them off
+/// the canvas edges.
+pub fn step(balls: &mut [Metaball], dt: f32) {
+    for ball in balls.iter_mut() {
+        ball.x += ball.vx * dt;
+        ball.y += ball.vy * dt;
+
+        if ball.x < 0.0 || ball.x > 1.0 {
+            ball.x = ball.x.clamp(0.0, 1.0);
+            ball.vx = -ball.vx;
+        }
+        if ball.y < 0.0 || ball.y > 1.0 {
+            ball.y = ball.y.clamp(0.0, 1.0);
+            ball.vy = -ball.vy;
+        }
+    }
+}
+
+/// Overlap, in normalized coordinates, that touching balls are left with, so
+/// resting contacts don't jitter from being pushed apart every step.
+pub const COLLISION_SLOP: f32 = 0.002;
+
+/// Bounces overlapping balls off each other as equal masses, scaling the
+/// velocity along the contact normal by `restitution` (1 is perfectly
+/// elastic), and pushes them apart to within `COLLISION_SLOP`. Every pair is
+/// checked, which is cheap for `MAX_METABALLS` balls.
+pub fn collide(balls: &mut [Metaball], restitution: f32) {
+    for i in 0..balls.len() {
+        let (head, tail) = balls.split_at_mut(i + 1);
+        let a = &mut head[i];
+        for b in tail.iter_mut() {
+            let (dx, dy) = (b.x - a.x, b.y - a.y);
+            let distance = dx.hypot(dy);
+            let overlap = a.extent() + b.extent() - distance;
+            if overlap <= 0.0 || distance <= 0.0 {
+                continue;
+            }
+            let (nx, ny) = (dx / distance, dy / distance);
+            let approach = (b.vx - a.vx) * nx + (b.vy - a.vy) * ny;
+            if approach < 0.0 {
+                let impulse = -(1.0 + restitution) * approach / 2.0;
+                a.vx -= impulse * nx;
+                a.vy -= impulse * ny;
+                b.vx += impulse * nx;
+                b.vy += impulse * ny;
+            }
+            let separation = (overlap - COLLISION_SLOP).max(0.0) / 2.0;
+            a.x -= separation * nx;
+            a.y -= separation * ny;
+            b.x += separation * nx;
+            b.y += separation * ny;
+        }
+    }
+}
+
+/// Most sub-steps run for one frame. Any time beyond that is dropped, so a
+/// long stall can't make the simulation fall further behind.
+pub const MAX_SUBSTEPS: usize = 8;
+
+/// Splits `dt` into equal sub-steps no longer than `max_step`, returning the
+/// sub-step count and length.
+pub fn substeps(dt: f32, max_step: f32) -> (usize, f32) {
+    if dt <= 0.0 {
+        return (0, 0.0);
+    }
+    if max_step <= 0.0 || dt <= max_step {
+        return (1, dt);
+    }
+    let count = ((dt / max_step).ceil() as usize).min(MAX_SUBSTEPS);
+    (count, (dt / count as f32).min(max_step))
+}
+
+/// Advances the simulation by `dt` in sub-steps of at most `max_step`
+/// seconds and returns the number of sub-steps run.
+pub fn integrate(balls: &mut [Metaball], dt: f32, max_step: f32) -> usize {
+    integrate_with_collisions(balls, dt, max_step, None)
+}
+
+/// Like `integrate`, resolving collisions with `restitution` after every
+/// sub-step when one is given.
+pub fn integrate_with_collisions(balls: &mut [Metaball], dt: f32, max_step: f32, restitution: Option<f32>) -> usize {
+    let (count, step_dt) = substeps(dt, max_step);
+    for _ in 0..count {
+        step(balls, step_dt);
+        if let Some(restitution) = restitution {
+            collide(balls, restitution);
+        }
+    }
+    count
+}
@@ -0,0 +1,80 @@
+/// Rate the mouse speed driving `set_mouse_speed_response` is smoothed with,
+/// in 1/seconds.
+pub const MOUSE_SPEED_SMOOTHING: f32 = 6.0;
+
+/// Largest radius factor the mouse speed response grows the spheres to.
+const MAX_SPEED_SCALE: f32 = 3.0;
+
+/// Factor one notch of the wheel (100 pixels of `deltaY`) changes the speed
+/// by, faster when scrolling up.
+pub const WHEEL_SPEED_STEP: f32 = 1.1;
+
+/// Range the wheel keeps the speed in.
+const WHEEL_SPEED_RANGE: (f32, f32) = (0.1, 10.0);
+
+/// The pointer position relative to the canvas in CSS pixels, with its
+/// velocity estimated from consecutive samples.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointerTracker {
+    pub x: f32,
+    pub y: f32,
+    /// CSS pixels per second.
+    pub vx: f32,
+    pub vy: f32,
+    last_time: Option<f64>,
+    /// Samples received since the effect started.
+    pub sample_count: usize,
+    /// Whether the primary button is held, as of the last sample, press or
+    /// release.
+    pub pressed: bool,
+}
+
+impl PointerTracker {
+    pub fn new() -> PointerTracker {
+        PointerTracker::default()
+    }
+
+    /// Records a position at `time` seconds.
+    pub fn push(&mut self, x: f32, y: f32, time: f64) {
+        if let Some(last_time) = self.last_time {
+            let dt = (time - last_time) as f32;
+            if dt > 0.0 {
+                self.vx = (x - self.x) / dt;
+                self.vy = (y - self.y) / dt;
+            }
+        }
+        self.x = x;
+        self.y = y;
+        self.last_time = Some(time);
+        self.sample_count += 1;
+    }
+
+    /// The time of the last sample in seconds, None before the first.
+    pub fn last_time(&self) -> Option<f64> {
+        self.last_time
+    }
+
+    /// Records the samples of one event in order, e.g. its coalesced events.
+    pub fn push_all(&mut self, samples: &[(f32, f32, f64)]) {
+        for &(x, y, time) in samples {
+            self.push(x, y, time);
+        }
+    }
+}
+
+impl Default for PointerTracker {
+    fn default() -> PointerTracker {
+        PointerTracker { x: 0.0, y: 0.0, vx: 0.0, vy: 0.0, last_time: None, sample_count: 0, pressed: false }
+    }
+}
+
+/// The radius factor of the mouse-driven spheres moving at `speed`, in
+/// normalized coordinates per second, with a response of `factor`.
+pub fn speed_scale(speed: f32, factor: f32) -> f32 {
+    (1.0 + factor * speed).clamp(0.0, MAX_SPEED_SCALE)
+}
+
+/// The speed after scrolling the wheel by `delta_y` pixels at `speed`.
+pub fn wheel_speed(speed: f32, delta_y: f32) -> f32 {
+    (speed * WHEEL_SPEED_STEP.powf(-delta_y / 100.0)).clamp(WHEEL_SPEED_RANGE.0, WHEEL_SPEED_RANGE.1)
+}
@@ -0,0 +1,63 @@
+use crate::easing::ease_in_out;
+
+/// How far a fresh pulse pulls the output toward its accent color.
+pub const PULSE_STRENGTH: f32 = 0.6;
+
+/// A flash toward an accent color, easing back over `duration` seconds of
+/// wall clock time from `start`, so it ends whatever the speed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorPulse {
+    pub color: (f32, f32, f32),
+    pub start: f64,
+    pub duration: f32,
+}
+
+impl ColorPulse {
+    /// The pull toward the accent at wall clock time `time`, from
+    /// `PULSE_STRENGTH` when triggered down to 0 at the end.
+    pub fn strength(&self, time: f64) -> f32 {
+        if self.duration <= 0.0 {
+            return 0.0;
+        }
+        let t = (time - self.start) as f32 / self.duration;
+        if !(0.0..1.0).contains(&t) {
+            return 0.0;
+        }
+        PULSE_STRENGTH * (1.0 - ease_in_out(t))
+    }
+
+    pub fn is_done(&self, time: f64) -> bool {
+        (time - self.start) as f32 >= self.duration
+    }
+}
+
+/// The accent color and strength of `pulses` combined at `time`: the accents
+/// weighted by their strengths, pulling as much as the pulses would one
+/// after another.
+pub fn combined_pulse(pulses: &[ColorPulse], time: f64) -> ((f32, f32, f32), f32) {
+    let mut total = 0.0;
+    let mut remaining = 1.0;
+    let mut color = (0.0, 0.0, 0.0);
+    for pulse in pulses {
+        let strength = pulse.strength(time);
+        total += strength;
+        remaining *= 1.0 - strength;
+        color.0 += pulse.color.0 * strength;
+        color.1 += pulse.color.1 * strength;
+        color.2 += pulse.color.2 * strength;
+    }
+    if total <= 0.0 {
+        return ((0.0, 0.0, 0.0), 0.0);
+    }
+    ((color.0 / total, color.1 / total, color.2 / total), 1.0 - remaining)
+}
+
+/// `color` pulled toward the combined accent by its strength, as the
+/// fragment shader does with the output.
+pub fn apply_pulse(color: (f32, f32, f32), (accent, strength): ((f32, f32, f32), f32)) -> (f32, f32, f32) {
+    (
+        color.0 + (accent.0 - color.0) * strength,
+        color.1 + (accent.1 - color.1) * strength,
+        color.2 + (accent.2 - color.2) * strength,
+    )
+}
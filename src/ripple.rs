@@ -0,0 +1,83 @@
+/// Number of ripples the fragment shader has uniform slots for.
+pub const MAX_RIPPLES: usize = 4;
+
+/// Amplitude below which a ripple is considered gone.
+const AMPLITUDE_CUTOFF: f32 = 0.01;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RippleParams {
+    /// Distance between wave crests, as a fraction of the shorter canvas side.
+    pub wavelength: f32,
+    /// How fast the wave front travels, in shorter canvas sides per second.
+    pub speed: f32,
+    /// Exponential amplitude decay rate in 1/seconds.
+    pub decay: f32,
+}
+
+impl Default for RippleParams {
+    fn default() -> RippleParams {
+        RippleParams { wavelength: 0.05, speed: 0.3, decay: 2.0 }
+    }
+}
+
+/// Amplitude of a ripple `age` seconds after it was triggered, which is 0
+/// once its lifetime is over.
+pub fn amplitude(age: f32, decay: f32) -> f32 {
+    if age < 0.0 || age >= lifetime(decay) {
+        return 0.0;
+    }
+    (-decay * age).exp()
+}
+
+/// Seconds until the amplitude falls below the cutoff.
+pub fn lifetime(decay: f32) -> f32 {
+    -AMPLITUDE_CUTOFF.ln() / decay.max(f32::EPSILON)
+}
+
+struct Ripple {
+    x: f32,
+    y: f32,
+    start: f64,
+}
+
+/// Transient ripples, with the oldest one replaced once all slots are in use.
+#[derive(Default)]
+pub struct Ripples {
+    pub params: RippleParams,
+    ripples: Vec<Ripple>,
+}
+
+impl Ripples {
+    pub fn new() -> Ripples {
+        Ripples::default()
+    }
+
+    pub fn trigger(&mut self, x: f32, y: f32, now: f64) {
+        if self.ripples.len() >= MAX_RIPPLES {
+            self.ripples.remove(0);
+        }
+        self.ripples.push(Ripple { x, y, start: now });
+    }
+
+    pub fn len(&self) -> usize {
+        self.ripples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ripples.is_empty()
+    }
+
+    /// Drops the expired ripples and flattens the rest into the
+    /// `vec4 ripples[MAX_RIPPLES]` uniform layout (x, y, age, amplitude).
+    pub fn update(&mut self, now: f64) -> Vec<f32> {
+        let decay = self.params.decay;
+        self.ripples.retain(|ripple| amplitude((now - ripple.start) as f32, decay) > 0.0);
+
+        let mut uniforms = vec![0.0; MAX_RIPPLES * 4];
+        for (i, ripple) in self.ripples.iter().enumerate() {
+            let age = (now - ripple.start) as f32;
+            uniforms[i * 4..i * 4 + 4].copy_from_slice(&[ripple.x, ripple.y, age, amplitude(age, decay)]);
+        }
+        uniforms
+    }
+}
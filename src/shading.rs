@@ -0,0 +1,33 @@
+/// Color the blobs are filled with.
+pub const FILL_COLOR: (f32, f32, f32) = (1.0, 1.0, 1.0);
+
+/// Depth of the rim taking the edge color, in scene units inside the surface.
+pub const EDGE_BAND: f32 = 0.3;
+
+/// Posterizes `intensity` in [0, 1] into `levels` flat bands spanning 0 to 1,
+/// matching the cel shading in the fragment shader. Fewer than 2 levels leave
+/// the intensity unchanged.
+pub fn quantize(intensity: f32, levels: u32) -> f32 {
+    if levels < 2 {
+        return intensity;
+    }
+    let levels = levels as f32;
+    let band = (intensity.clamp(0.0, 1.0) * levels).floor().min(levels - 1.0);
+    band / (levels - 1.0)
+}
+
+/// Gamma corrects a color channel in [0, 1] like the fragment shader, which
+/// raises it to `1 / gamma`. A gamma of 1 leaves it unchanged.
+pub fn apply_gamma(value: f32, gamma: f32) -> f32 {
+    value.powf(1.0 / gamma)
+}
+
+/// The color of the surface before lighting at a signed `distance` from the
+/// isoline, in scene units and negative inside: `edge` on the isoline blending
+/// into `fill` over `EDGE_BAND`.
+pub fn surface_color(fill: (f32, f32, f32), edge: (f32, f32, f32), distance: f32) -> (f32, f32, f32) {
+    let t = (-distance / EDGE_BAND).clamp(0.0, 1.0);
+    let t = t * t * (3.0 - 2.0 * t);
+    let mix = |edge: f32, fill: f32| edge + (fill - edge) * t;
+    (mix(edge.0, fill.0), mix(edge.1, fill.1), mix(edge.2, fill.2))
+}
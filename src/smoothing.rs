@@ -0,0 +1,99 @@
+use wasm_bindgen::prelude::*;
+
+/// Cutoff frequency, in Hz, the 1€ filter smooths its speed estimate with.
+pub const ONE_EURO_DERIVATIVE_CUTOFF: f32 = 1.0;
+
+/// Selects the filter passed to `set_smoothing_algorithm`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SmoothingKind {
+    None,
+    Exponential,
+    OneEuro,
+}
+
+/// How the pointer position is smoothed before the effect follows it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Smoothing {
+    None,
+    /// Moves `alpha` of the way to the pointer each frame.
+    Exponential { alpha: f32 },
+    /// The 1€ filter: a low pass whose cutoff, in Hz, rises from
+    /// `min_cutoff` by `beta` per unit of speed, so slow movements are
+    /// smoothed strongly and fast ones follow with little lag.
+    OneEuro { min_cutoff: f32, beta: f32 },
+}
+
+impl Smoothing {
+    /// The filter of `kind` with its parameters `a` and `b`: the alpha of
+    /// `Exponential`, or the min cutoff and beta of `OneEuro`.
+    pub fn from_kind(kind: SmoothingKind, a: f32, b: f32) -> Smoothing {
+        match kind {
+            SmoothingKind::None => Smoothing::None,
+            SmoothingKind::Exponential => Smoothing::Exponential { alpha: a.clamp(0.0, 1.0) },
+            SmoothingKind::OneEuro => Smoothing::OneEuro { min_cutoff: a.max(1e-3), beta: b.max(0.0) },
+        }
+    }
+}
+
+/// The smoothing factor of a low pass with `cutoff` Hz over `dt` seconds.
+fn low_pass_alpha(cutoff: f32, dt: f32) -> f32 {
+    let tau = 1.0 / (2.0 * std::f32::consts::PI * cutoff);
+    1.0 / (1.0 + tau / dt)
+}
+
+/// Filters one coordinate with a `Smoothing`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Filter {
+    /// The last output, None before the first sample.
+    value: Option<f32>,
+    /// The smoothed speed of the 1€ filter, in units per second.
+    speed: f32,
+}
+
+impl Filter {
+    /// Filters `value` sampled `dt` seconds after the previous one. The
+    /// first sample passes through unchanged.
+    pub fn filter(&mut self, smoothing: Smoothing, value: f32, dt: f32) -> f32 {
+        let previous = match self.value {
+            Some(previous) if smoothing != Smoothing::None => previous,
+            _ => {
+                self.value = Some(value);
+                return value;
+            },
+        };
+        if dt <= 0.0 {
+            return previous;
+        }
+        let filtered = match smoothing {
+            Smoothing::None => value,
+            Smoothing::Exponential { alpha } => previous + (value - previous) * alpha,
+            Smoothing::OneEuro { min_cutoff, beta } => {
+                let speed = (value - previous) / dt;
+                self.speed += (speed - self.speed) * low_pass_alpha(ONE_EURO_DERIVATIVE_CUTOFF, dt);
+                let cutoff = min_cutoff + beta * self.speed.abs();
+                previous + (value - previous) * low_pass_alpha(cutoff, dt)
+            },
+        };
+        self.value = Some(filtered);
+        filtered
+    }
+}
+
+/// Filters a position with a `Smoothing`, each axis on its own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointerFilter {
+    pub smoothing: Smoothing,
+    x: Filter,
+    y: Filter,
+}
+
+impl PointerFilter {
+    pub fn new(smoothing: Smoothing) -> PointerFilter {
+        PointerFilter { smoothing, x: Filter::default(), y: Filter::default() }
+    }
+
+    pub fn filter(&mut self, (x, y): (f32, f32), dt: f32) -> (f32, f32) {
+        (self.x.filter(self.smoothing, x, dt), self.y.filter(self.smoothing, y, dt))
+    }
+}
@@ -0,0 +1,103 @@
+use wasm_bindgen::prelude::*;
+use std::collections::VecDeque;
+
+/// Number of frame time samples kept.
+const SAMPLE_COUNT: usize = 60;
+
+/// A frame taking this many times the expected interval counts as jank.
+const JANK_FACTOR: f64 = 1.5;
+
+/// Frame interval assumed until enough samples are collected.
+const DEFAULT_FRAME_INTERVAL: f64 = 1.0 / 60.0;
+
+/// Shortest interval the estimate goes down to, so frames repeating the same
+/// timestamp can't make every later frame count as jank.
+const MIN_FRAME_INTERVAL: f64 = 1.0 / 1000.0;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderStats {
+    /// Average wall clock time between frames.
+    pub frame_time_ms: f64,
+    /// Frames estimated to have been skipped since the effect started.
+    pub dropped_frames: u32,
+    /// Average GPU time of the recently timed frames, or None without timer
+    /// query support.
+    pub gpu_frame_time_ms: Option<f64>,
+}
+
+/// Collects frame time samples.
+pub struct FrameStats {
+    samples: VecDeque<f64>,
+    gpu_samples: VecDeque<f64>,
+    dropped_frames: u32,
+}
+
+impl FrameStats {
+    pub fn new() -> FrameStats {
+        FrameStats::default()
+    }
+
+    /// The refresh interval, estimated from the median of the samples.
+    pub fn expected_interval(&self) -> f64 {
+        if self.samples.len() < SAMPLE_COUNT / 4 {
+            return DEFAULT_FRAME_INTERVAL;
+        }
+        let mut samples: Vec<f64> = self.samples.iter().cloned().collect();
+        samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        samples[samples.len() / 2].max(MIN_FRAME_INTERVAL)
+    }
+
+    /// Records the seconds since the previous frame and returns how many
+    /// frames were dropped before this one.
+    pub fn record(&mut self, dt: f64) -> u32 {
+        let expected = self.expected_interval();
+        let dropped = if expected > 0.0 && dt > expected * JANK_FACTOR {
+            ((dt / expected).round() as u32).saturating_sub(1).max(1)
+        } else {
+            0
+        };
+        self.dropped_frames = self.dropped_frames.saturating_add(dropped);
+
+        if self.samples.len() == SAMPLE_COUNT {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(dt);
+
+        dropped
+    }
+
+    /// Records the GPU seconds a frame took, which arrive a few frames late.
+    pub fn record_gpu_time(&mut self, seconds: f64) {
+        if self.gpu_samples.len() == SAMPLE_COUNT {
+            self.gpu_samples.pop_front();
+        }
+        self.gpu_samples.push_back(seconds);
+    }
+
+    pub fn render_stats(&self) -> RenderStats {
+        let frame_time = match self.samples.len() {
+            0 => 0.0,
+            len => self.samples.iter().sum::<f64>() / len as f64,
+        };
+        let gpu_frame_time = match self.gpu_samples.len() {
+            0 => None,
+            len => Some(self.gpu_samples.iter().sum::<f64>() / len as f64),
+        };
+        RenderStats {
+            frame_time_ms: frame_time * 1000.0,
+            dropped_frames: self.dropped_frames,
+            gpu_frame_time_ms: gpu_frame_time.map(|time| time * 1000.0),
+        }
+    }
+}
+
+impl Default for FrameStats {
+    fn default() -> FrameStats {
+        FrameStats {
+            samples: VecDeque::with_capacity(SAMPLE_COUNT),
+            gpu_samples: VecDeque::new(),
+            dropped_frames: 0,
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use crate::easing::ease_toward;
+use crate::metaball::{Metaball, MAX_METABALLS};
+
+/// Radius of the head of a pointer tail, tapering to half toward the end.
+const TAIL_RADIUS: f32 = 0.06;
+
+/// A chain of balls following the pointer, each easing toward the one in
+/// front of it.
+pub struct PointerTail {
+    segments: Vec<(f32, f32)>,
+    /// Time constant of every segment's easing, in seconds.
+    lag: f32,
+}
+
+impl PointerTail {
+    /// A tail of up to `MAX_METABALLS` segments, all starting at `start`.
+    pub fn new(segments: usize, lag: f32, start: (f32, f32)) -> PointerTail {
+        PointerTail { segments: vec![start; segments.min(MAX_METABALLS)], lag: lag.max(0.001) }
+    }
+
+    /// Moves the head toward `target` and every other segment toward the
+    /// previous one's new position over `dt` seconds.
+    pub fn update(&mut self, target: (f32, f32), dt: f32) {
+        let rate = 1.0 / self.lag;
+        let mut leader = target;
+        for segment in &mut self.segments {
+            segment.0 = ease_toward(segment.0, leader.0, rate, dt);
+            segment.1 = ease_toward(segment.1, leader.1, rate, dt);
+            leader = *segment;
+        }
+    }
+
+    pub fn lag(&self) -> f32 {
+        self.lag
+    }
+
+    pub fn positions(&self) -> &[(f32, f32)] {
+        &self.segments
+    }
+
+    /// The segments as balls, from the head.
+    pub fn balls(&self) -> Vec<Metaball> {
+        let count = self.segments.len() as f32;
+        self.segments.iter()
+            .enumerate()
+            .map(|(i, &(x, y))| Metaball::new(x, y, TAIL_RADIUS * (1.0 - i as f32 / count / 2.0)))
+            .collect()
+    }
+}
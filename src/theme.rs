@@ -0,0 +1,70 @@
+use crate::easing::ease_in_out;
+use crate::shading::FILL_COLOR;
+
+/// The colors the effect is drawn in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+    pub fill: (f32, f32, f32),
+    pub edge: (f32, f32, f32),
+    pub background: (f32, f32, f32),
+}
+
+impl Palette {
+    /// Blends toward `other` by `t` in [0, 1].
+    pub fn mix(&self, other: &Palette, t: f32) -> Palette {
+        let mix = |a: (f32, f32, f32), b: (f32, f32, f32)| (
+            a.0 + (b.0 - a.0) * t,
+            a.1 + (b.1 - a.1) * t,
+            a.2 + (b.2 - a.2) * t,
+        );
+        Palette {
+            fill: mix(self.fill, other.fill),
+            edge: mix(self.edge, other.edge),
+            background: mix(self.background, other.background),
+        }
+    }
+}
+
+/// White blobs on black, as drawn without a theme.
+pub const DEFAULT_PALETTE: Palette = Palette {
+    fill: FILL_COLOR,
+    edge: FILL_COLOR,
+    background: (0.0, 0.0, 0.0),
+};
+
+pub const NIGHT: Palette = Palette {
+    fill: (0.55, 0.6, 0.85),
+    edge: (0.2, 0.25, 0.6),
+    background: (0.02, 0.02, 0.08),
+};
+
+pub const DAWN: Palette = Palette {
+    fill: (1.0, 0.82, 0.72),
+    edge: (0.95, 0.45, 0.4),
+    background: (0.25, 0.15, 0.25),
+};
+
+pub const DAY: Palette = Palette {
+    fill: (1.0, 1.0, 0.95),
+    edge: (0.4, 0.7, 1.0),
+    background: (0.55, 0.75, 0.95),
+};
+
+pub const DUSK: Palette = Palette {
+    fill: (1.0, 0.7, 0.45),
+    edge: (0.75, 0.3, 0.45),
+    background: (0.2, 0.08, 0.18),
+};
+
+/// The palettes of the day, each reached at 0, 6, 12 and 18 o'clock.
+const TIMES_OF_DAY: [Palette; 4] = [NIGHT, DAWN, DAY, DUSK];
+
+/// The palette at `hour` in [0, 24), eased between the neighboring times of
+/// day and wrapping from dusk back to night at midnight.
+pub fn time_of_day_palette(hour: f32) -> Palette {
+    let span = 24.0 / TIMES_OF_DAY.len() as f32;
+    let position = hour.rem_euclid(24.0) / span;
+    let index = position.floor() as usize % TIMES_OF_DAY.len();
+    let next = (index + 1) % TIMES_OF_DAY.len();
+    TIMES_OF_DAY[index].mix(&TIMES_OF_DAY[next], ease_in_out(position.fract()))
+}
@@ -0,0 +1,81 @@
+use crate::metaball::{Metaball, MAX_METABALLS};
+use crate::viewport::Region;
+
+/// Balls allowed when rendering in tiles. Every tile still evaluates at most
+/// `MAX_METABALLS` of them.
+pub const MAX_TILED_METABALLS: usize = 256;
+
+/// Distance beyond a ball's outline, as a fraction of the shorter canvas side,
+/// where its field contribution becomes negligible (below 1%) with the
+/// shader's smoothness of 2 and scene unit of 4: ln(100) / (2 * 4).
+pub const FIELD_REACH: f32 = 0.58;
+
+/// Tiles allowed along each side of the grid.
+pub const MAX_TILES_PER_SIDE: u32 = 64;
+
+/// Bins the balls into a `tiles_x` x `tiles_y` grid over a `width` x `height`
+/// canvas. Each tile gets the indices of the balls that reach into it,
+/// row by row from the top-left tile.
+pub fn bin_balls(balls: &[Metaball], tiles_x: u32, tiles_y: u32, width: f32, height: f32, reach: f32) -> Vec<Vec<usize>> {
+    let mut tiles = vec![vec![]; (tiles_x * tiles_y) as usize];
+    if tiles.is_empty() {
+        return tiles;
+    }
+
+    let shorter = width.min(height);
+    for (index, ball) in balls.iter().enumerate() {
+        let extent = ball.extent() + reach;
+        let extent_x = extent * shorter / width;
+        let extent_y = extent * shorter / height;
+
+        let tile_range = |from: f32, to: f32, count: u32| {
+            let first = (from * count as f32).floor().max(0.0) as u32;
+            let last = ((to * count as f32).floor().max(0.0) as u32).min(count - 1);
+            first..=last
+        };
+        if ball.x + extent_x < 0.0 || ball.x - extent_x > 1.0 || ball.y + extent_y < 0.0 || ball.y - extent_y > 1.0 {
+            continue;
+        }
+        for tile_y in tile_range(ball.y - extent_y, ball.y + extent_y, tiles_y) {
+            for tile_x in tile_range(ball.x - extent_x, ball.x + extent_x, tiles_x) {
+                tiles[(tile_y * tiles_x + tile_x) as usize].push(index);
+            }
+        }
+    }
+
+    tiles
+}
+
+/// Cuts the bins of `bin_balls` holding more than `MAX_METABALLS` balls down
+/// to the ones whose outline comes closest to the tile's center, which
+/// contribute the most there. Returns how many tiles were cut.
+pub fn limit_bins(balls: &[Metaball], bins: &mut [Vec<usize>], tiles_x: u32, tiles_y: u32, width: f32, height: f32) -> usize {
+    let shorter = width.min(height);
+    let mut cut = 0;
+    for (index, bin) in bins.iter_mut().enumerate() {
+        if bin.len() <= MAX_METABALLS {
+            continue;
+        }
+        let center_x = (index as u32 % tiles_x) as f32 / tiles_x as f32 + 0.5 / tiles_x as f32;
+        let center_y = (index as u32 / tiles_x) as f32 / tiles_y as f32 + 0.5 / tiles_y as f32;
+        // in fractions of the shorter side, like the extents
+        let gap = |ball: &Metaball| {
+            let dx = (ball.x - center_x) * width / shorter;
+            let dy = (ball.y - center_y) * height / shorter;
+            dx.hypot(dy) - ball.extent()
+        };
+        bin.sort_by(|&a, &b| gap(&balls[a]).total_cmp(&gap(&balls[b])));
+        bin.truncate(MAX_METABALLS);
+        bin.sort_unstable();
+        cut += 1;
+    }
+    cut
+}
+
+/// The part of `region` covered by the tile at `index` of the grid.
+pub fn tile_region(region: &Region, tiles_x: u32, tiles_y: u32, index: usize) -> Region {
+    let tile_w = region.width / tiles_x as f32;
+    let tile_h = region.height / tiles_y as f32;
+    let (tile_x, tile_y) = (index as u32 % tiles_x, index as u32 / tiles_x);
+    Region::new(region.x + tile_x as f32 * tile_w, region.y + tile_y as f32 * tile_h, tile_w, tile_h)
+}
@@ -0,0 +1,46 @@
+/// How steeply the field pass ramps its alpha across the blob edge, per scene
+/// unit of distance. The ramp has to span a few reduced resolution pixels for
+/// the bilinear upscaling to reconstruct the edge position.
+pub const EDGE_SCALE: f32 = 4.0;
+
+/// The alpha the field pass writes for a distance to the blob surface, 0.5
+/// being the edge.
+pub fn edge_alpha(distance: f32) -> f32 {
+    (0.5 - distance * EDGE_SCALE).clamp(0.0, 1.0)
+}
+
+/// Half the alpha change across one output pixel of a region whose shorter
+/// side is `shorter_side` pixels, which is one scene unit per quarter of it.
+pub fn edge_width(shorter_side: f32) -> f32 {
+    EDGE_SCALE * 2.0 / shorter_side.max(1.0)
+}
+
+/// The coverage the upscaling pass thresholds an interpolated `alpha` to,
+/// antialiased over one output pixel.
+pub fn crisp_coverage(alpha: f32, edge_width: f32) -> f32 {
+    let t = ((alpha - 0.5 + edge_width) / (edge_width * 2.0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// The size of the reduced resolution target for a `width` x `height` region.
+pub fn scaled_size(width: f32, height: f32, scale: f32) -> (i32, i32) {
+    (((width * scale).round() as i32).max(1), ((height * scale).round() as i32).max(1))
+}
+
+/// The size of the target the scene is rendered into before upscaling to a
+/// `width` x `height` region, or None when it's rendered directly. A pixel
+/// art resolution takes precedence over the resolution scale.
+pub fn render_size(width: f32, height: f32, scale: f32, pixel_art: Option<(u32, u32)>) -> Option<(i32, i32)> {
+    match pixel_art {
+        Some((art_width, art_height)) => Some((art_width as i32, art_height as i32)),
+        None if scale < 1.0 => Some(scaled_size(width, height, scale)),
+        None => None,
+    }
+}
+
+/// The source texel nearest filtering picks for output pixel `pixel` when
+/// stretching `source_size` texels over `output_size` pixels.
+pub fn nearest_texel(pixel: u32, output_size: u32, source_size: u32) -> u32 {
+    let position = (pixel as f32 + 0.5) / output_size as f32;
+    ((position * source_size as f32).floor() as u32).min(source_size - 1)
+}
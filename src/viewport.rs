@@ -0,0 +1,67 @@
+/// A rectangle of the drawing buffer in pixels, with the origin at the
+/// top-left corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Region {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Region {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Region {
+        Region { x, y, width, height }
+    }
+
+    /// The region inset by `padding` on every side.
+    pub fn padded(width: f32, height: f32, padding: f32) -> Region {
+        let padding = padding.max(0.0).min(width / 2.0).min(height / 2.0);
+        Region::new(padding, padding, width - padding * 2.0, height - padding * 2.0)
+    }
+
+    /// The largest region of aspect ratio `aspect` (width / height) centered
+    /// in this one, leaving bars at the top and bottom or at the sides. An
+    /// aspect of 0 or less keeps the whole region.
+    pub fn letterboxed(&self, aspect: f32) -> Region {
+        if aspect <= 0.0 || self.height <= 0.0 {
+            return *self;
+        }
+        let width = self.width.min(self.height * aspect);
+        let height = self.height.min(self.width / aspect);
+        Region::new(
+            self.x + (self.width - width) / 2.0,
+            self.y + (self.height - height) / 2.0,
+            width,
+            height,
+        )
+    }
+
+    /// The overlap with `other`, empty where they don't overlap.
+    pub fn intersect(&self, other: &Region) -> Region {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        Region::new(x, y, (right - x).max(0.0), (bottom - y).max(0.0))
+    }
+
+    /// Maps a drawing buffer position to normalized coordinates of the region.
+    pub fn normalize(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            (x - self.x) / self.width.max(1.0),
+            (y - self.y) / self.height.max(1.0),
+        )
+    }
+
+    /// The bottom-left origin WebGL uses for viewports and gl_FragCoord.
+    pub fn gl_origin(&self, buffer_height: f32) -> (f32, f32) {
+        (self.x, buffer_height - self.y - self.height)
+    }
+}
+
+/// Snaps a normalized position to the center of its cell in a `cells_x` x
+/// `cells_y` grid over the region.
+pub fn snap_to_grid(x: f32, y: f32, cells_x: u32, cells_y: u32) -> (f32, f32) {
+    let snap = |value: f32, cells: u32| ((value * cells as f32).floor() + 0.5) / cells as f32;
+    (snap(x, cells_x), snap(y, cells_y))
+}
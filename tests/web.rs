@@ -3,11 +3,1468 @@
 #![cfg(target_arch = "wasm32")]
 
 extern crate wasm_bindgen_test;
+use wasm_bindgen::JsCast;
 use wasm_bindgen_test::*;
 
+use rust_wasm_webgl_mdn_tutorial::*;
+
 wasm_bindgen_test_configure!(run_in_browser);
 
+fn create_canvas() -> web_sys::HtmlCanvasElement {
+    web_sys::window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .create_element("canvas")
+        .unwrap()
+        .dyn_into::<web_sys::HtmlCanvasElement>()
+        .unwrap()
+}
+
 #[wasm_bindgen_test]
 fn pass() {
     assert_eq!(1 + 1, 2);
 }
+
+#[wasm_bindgen_test]
+fn pointer_listener_attaches_to_selected_target() {
+    let canvas = create_canvas();
+    let window = web_sys::window().unwrap();
+    let document = window.document().unwrap();
+
+    let target: wasm_bindgen::JsValue = pointer_event_target(PointerTarget::Canvas, &canvas).into();
+    assert_eq!(target, wasm_bindgen::JsValue::from(canvas.clone()));
+
+    let target: wasm_bindgen::JsValue = pointer_event_target(PointerTarget::Document, &canvas).into();
+    assert_eq!(target, wasm_bindgen::JsValue::from(document.clone()));
+
+    let target: wasm_bindgen::JsValue = pointer_event_target(PointerTarget::Window, &canvas).into();
+    assert_eq!(target, wasm_bindgen::JsValue::from(window));
+
+    let mousemove = || web_sys::MouseEvent::new("mousemove").unwrap();
+    let on_canvas = Effect::headless(Config::new());
+    listen_to_pointer(&on_canvas, PointerTarget::Canvas, &canvas, false).unwrap();
+    let on_document = Effect::headless(Config::new());
+    listen_to_pointer(&on_document, PointerTarget::Document, &canvas, false).unwrap();
+
+    // The canvas isn't in the document, so its events don't bubble there.
+    canvas.dispatch_event(&mousemove()).unwrap();
+    assert_eq!(on_canvas.pointer().sample_count, 1);
+    assert_eq!(on_document.pointer().sample_count, 0);
+
+    document.dispatch_event(&mousemove()).unwrap();
+    assert_eq!(on_canvas.pointer().sample_count, 1);
+    assert_eq!(on_document.pointer().sample_count, 1);
+}
+
+#[wasm_bindgen_test]
+fn releasing_the_button_without_moving_ends_the_press() {
+    let canvas = create_canvas();
+    let effect = Effect::headless(Config::new());
+    listen_to_pointer(&effect, PointerTarget::Canvas, &canvas, false).unwrap();
+    let mouse_event = |event_name: &str, buttons: u16| {
+        let init = web_sys::MouseEventInit::new();
+        init.set_buttons(buttons);
+        web_sys::MouseEvent::new_with_mouse_event_init_dict(event_name, &init).unwrap()
+    };
+
+    canvas.dispatch_event(&mouse_event("mousedown", 1)).unwrap();
+    assert!(effect.pointer().pressed);
+
+    // released elsewhere, after the drag left the canvas
+    web_sys::window().unwrap().dispatch_event(&mouse_event("mouseup", 0)).unwrap();
+    assert!(!effect.pointer().pressed);
+    assert_eq!(effect.pointer().sample_count, 0);
+}
+
+#[wasm_bindgen_test]
+fn dithering_is_enabled_without_highp() {
+    let mut config = Config::new();
+    assert!(apply_precision_fallback(&mut config, 0));
+    assert!(config.dithering);
+
+    let mut config = Config::new();
+    assert!(!apply_precision_fallback(&mut config, 23));
+    assert!(!config.dithering);
+}
+
+#[wasm_bindgen_test]
+fn analyser_levels_drive_ball_radii() {
+    let bins = [0, 0, 255, 255];
+    let levels = band_levels(&bins, 2);
+    assert_eq!(levels, vec![0.0, 1.0]);
+
+    let balls = vec![Metaball::new(0.25, 0.5, 0.1), Metaball::new(0.75, 0.5, 0.1)];
+    let radii: Vec<f32> = apply_levels(&balls, &levels).iter().map(|ball| ball.radius).collect();
+    assert!(radii[0] < balls[0].radius);
+    assert!(radii[1] > balls[1].radius);
+}
+
+#[wasm_bindgen_test]
+fn connected_levels_scale_the_frame_radii() {
+    let mut effect = Effect::headless(Config::new());
+    effect.add_metaball(0.25, 0.5, 0.1).unwrap();
+    effect.add_metaball(0.75, 0.5, 0.1).unwrap();
+    effect.add_metaball(0.5, 0.25, 0.1).unwrap();
+    effect.connect_bins(vec![0, 0, 255, 255], 2);
+
+    let radii: Vec<f32> = effect.frame_balls().iter().map(|ball| ball.radius).collect();
+    // silence halves the radius, a full band makes it 1.5x, and the bands repeat
+    assert!((radii[0] - 0.05).abs() < 1e-6);
+    assert!((radii[1] - 0.15).abs() < 1e-6);
+    assert!((radii[2] - 0.05).abs() < 1e-6);
+    // the stored radii are left alone
+    assert!(effect.balls().iter().all(|ball| ball.radius == 0.1));
+}
+
+#[wasm_bindgen_test]
+fn padding_insets_the_rendered_region() {
+    let region = Region::padded(512.0, 512.0, 16.0);
+    assert_eq!(region, Region::new(16.0, 16.0, 480.0, 480.0));
+    assert!(region.width * region.height < 512.0 * 512.0);
+
+    assert_eq!(region.normalize(16.0, 16.0), (0.0, 0.0));
+    assert_eq!(region.normalize(256.0, 256.0), (0.5, 0.5));
+}
+
+#[wasm_bindgen_test]
+fn context_ids_are_tried_in_order() {
+    let ids: Vec<String> = vec!["webgl2".into(), "webgl".into(), "experimental-webgl".into()];
+    let mut attempts = vec![];
+    let found = first_available_context(&ids, |id| {
+        attempts.push(id.to_string());
+        if id == "experimental-webgl" || id == "webgl" { Some(id.len()) } else { None }
+    });
+
+    assert_eq!(found, Some(("webgl".to_string(), 5)));
+    assert_eq!(attempts, vec!["webgl2", "webgl"]);
+    assert_eq!(first_available_context(&ids, |_| None::<()>), None);
+}
+
+#[wasm_bindgen_test]
+fn hover_freeze_slows_down_while_hovered() {
+    let mut freeze = HoverFreeze::new();
+    freeze.enabled = true;
+    assert_eq!(freeze.update(0.1), 1.0);
+
+    freeze.hovered = true;
+    let first = freeze.update(0.1);
+    let second = freeze.update(0.1);
+    assert!(first < 1.0);
+    assert!(second < first);
+
+    freeze.hovered = false;
+    assert!(freeze.update(0.1) > second);
+}
+
+/// Replays the `effect.*` calls of an exported snippet.
+fn replay(code: &str, effect: &mut Effect) {
+    for line in code.lines().filter(|line| line.starts_with("effect.")) {
+        let call = &line["effect.".len()..line.len() - 2];
+        let (name, args) = call.split_at(call.find('(').unwrap());
+        let args: Vec<&str> = args[1..].split(", ").collect();
+        match name {
+            "set_speed" => effect.set_speed(args[0].parse().unwrap()),
+            "set_freeze_on_hover" => effect.set_freeze_on_hover(args[0].parse().unwrap()),
+            "set_padding" => effect.set_padding(args[0].parse().unwrap()),
+            "add_metaball" => effect.add_metaball(
+                args[0].parse().unwrap(),
+                args[1].parse().unwrap(),
+                args[2].parse().unwrap()
+            ).unwrap(),
+            _ => panic!("unexpected call: {}", line),
+        }
+    }
+}
+
+#[wasm_bindgen_test]
+fn exported_code_round_trips() {
+    let mut effect = Effect::headless(Config::new());
+    effect.set_speed(0.5);
+    effect.set_padding(12.0);
+    effect.add_metaball(0.25, 0.5, 0.1).unwrap();
+    effect.add_metaball(0.75, 0.5, 0.2).unwrap();
+
+    let code = effect.export_as_code();
+    assert!(code.contains("effect.set_speed(0.5);"));
+    assert!(!code.contains("set_freeze_on_hover"));
+
+    let mut replayed = Effect::headless(Config::new());
+    replay(&code, &mut replayed);
+    assert_eq!(replayed.export_as_code(), code);
+}
+
+#[wasm_bindgen_test]
+fn exports_leave_emitted_balls_to_their_emitters() {
+    let mut effect = Effect::headless(Config::new());
+    effect.add_emitter(0.5, 0.9, 10.0, 0.5, 0.3).unwrap();
+    for frame in 1..=30 {
+        effect.update(frame as f64 / 60.0);
+    }
+    assert!(effect.ball_count() > 0);
+    effect.add_metaball_shape(0.25, 0.5, 0.1, ShapeKind::Ellipse, 0.2, 0.1).unwrap();
+    let index = effect.ball_count() - 1;
+    effect.set_lifetime(index, 3.0).unwrap();
+
+    let code = effect.export_as_code();
+    assert!(code.contains("effect.add_emitter("));
+    assert!(!code.contains("add_metaball_with_lifetime"));
+    // the shape is the only exported ball, and keeps its lifetime
+    assert!(code.contains("effect.add_metaball_shape(0.25, 0.5, 0.1, ShapeKind.Ellipse, 0.2, 0.1);"));
+    assert!(code.contains("effect.set_lifetime(0, 3);"));
+}
+
+#[wasm_bindgen_test]
+fn adding_beyond_the_cap_is_rejected() {
+    let mut effect = Effect::headless(Config::new());
+    for _ in 0..MAX_METABALLS {
+        effect.add_metaball(0.5, 0.5, 0.1).unwrap();
+    }
+
+    let error = effect.add_metaball(0.5, 0.5, 0.1).unwrap_err().as_string().unwrap();
+    assert!(error.contains(&format!("{} of {}", MAX_METABALLS, MAX_METABALLS)));
+    assert_eq!(effect.ball_count(), MAX_METABALLS);
+}
+
+#[wasm_bindgen_test]
+fn ripple_amplitude_decays_to_zero() {
+    let decay = 2.0;
+    assert_eq!(ripple_amplitude(0.0, decay), 1.0);
+    assert!(ripple_amplitude(0.5, decay) < 1.0);
+    assert_eq!(ripple_amplitude(ripple_lifetime(decay), decay), 0.0);
+
+    let mut ripples = Ripples::new();
+    ripples.trigger(0.5, 0.5, 0.0);
+    ripples.update(0.1);
+    assert_eq!(ripples.len(), 1);
+    ripples.update(ripple_lifetime(ripples.params.decay) as f64 + 0.1);
+    assert_eq!(ripples.len(), 0);
+}
+
+#[wasm_bindgen_test]
+fn paused_physics_keeps_positions_while_time_advances() {
+    let mut effect = Effect::headless(Config::new());
+    effect.add_metaball(0.5, 0.5, 0.1).unwrap();
+    effect.set_velocity(0, 0.1, 0.0).unwrap();
+
+    effect.update(0.1);
+    let moved = effect.balls()[0];
+    assert!(moved.x > 0.5);
+
+    effect.pause_physics(true);
+    let time = effect.update(0.2);
+    let time_after = effect.update(0.3);
+    assert_eq!(effect.balls()[0], moved);
+    assert!(time_after > time);
+}
+
+#[wasm_bindgen_test]
+fn clear_strategies_frame_behavior() {
+    assert_eq!(ClearStrategy::full().frame(0.016), FrameClear { clear: true, fade_alpha: 0.0, blend: false });
+    assert_eq!(ClearStrategy::fade(0.2).frame(0.016), FrameClear { clear: false, fade_alpha: 0.2, blend: true });
+    assert_eq!(ClearStrategy::preserve().frame(0.016), FrameClear { clear: false, fade_alpha: 0.0, blend: true });
+
+    let trail = ClearStrategy::trail(3.0);
+    let short = trail.frame(0.016);
+    let long = trail.frame(0.033);
+    assert!(!short.clear && short.blend);
+    assert!(short.fade_alpha > 0.0 && short.fade_alpha < long.fade_alpha);
+    // two short frames fade as much as one long frame
+    let two_frames = 1.0 - (1.0 - trail.frame(0.5).fade_alpha).powi(2);
+    assert!((two_frames - trail.frame(1.0).fade_alpha).abs() < 1e-5);
+}
+
+#[wasm_bindgen_test]
+fn long_frame_gaps_count_as_dropped_frames() {
+    let mut stats = FrameStats::new();
+    for _ in 0..30 {
+        assert_eq!(stats.record(1.0 / 60.0), 0);
+    }
+    assert_eq!(stats.render_stats().dropped_frames, 0);
+
+    assert_eq!(stats.record(4.0 / 60.0), 3);
+    assert_eq!(stats.render_stats().dropped_frames, 3);
+}
+
+#[wasm_bindgen_test]
+fn repeated_timestamps_dont_count_as_jank() {
+    let mut stats = FrameStats::new();
+    for _ in 0..60 {
+        assert_eq!(stats.record(0.0), 0);
+    }
+    assert!(stats.expected_interval() > 0.0);
+
+    // Even absurd gaps only saturate the count.
+    stats.record(f64::MAX);
+    stats.record(f64::MAX);
+    assert_eq!(stats.render_stats().dropped_frames, u32::MAX);
+}
+
+#[wasm_bindgen_test]
+fn balls_are_binned_into_the_tiles_they_reach() {
+    let balls = vec![
+        Metaball::new(0.25, 0.25, 0.1),
+        Metaball::new(0.5, 0.5, 0.1),
+        Metaball::new(0.75, 0.25, 0.1),
+        Metaball::new(2.0, 2.0, 0.1),
+    ];
+    let bins = bin_balls(&balls, 2, 2, 512.0, 512.0, 0.0);
+    assert_eq!(bins, vec![vec![0, 1], vec![1, 2], vec![1], vec![1]]);
+
+    // on a wide canvas the same radius covers less of the normalized width
+    let bins = bin_balls(&[Metaball::new(0.45, 0.5, 0.1)], 2, 1, 1024.0, 256.0, 0.0);
+    assert_eq!(bins, vec![vec![0], vec![]]);
+
+    let region = Region::new(0.0, 0.0, 512.0, 512.0);
+    assert_eq!(tile_region(&region, 2, 2, 3), Region::new(256.0, 256.0, 256.0, 256.0));
+}
+
+#[wasm_bindgen_test]
+fn crowded_tiles_keep_the_balls_closest_to_their_center() {
+    // a far ball first, then more near the center than a tile can evaluate
+    let mut balls = vec![Metaball::new(0.9, 0.9, 0.05)];
+    balls.extend((0..MAX_METABALLS).map(|i| Metaball::new(0.5 + i as f32 * 0.001, 0.5, 0.05)));
+    let mut bins = bin_balls(&balls, 1, 1, 512.0, 512.0, FIELD_REACH);
+    assert_eq!(bins[0].len(), MAX_METABALLS + 1);
+
+    assert_eq!(limit_bins(&balls, &mut bins, 1, 1, 512.0, 512.0), 1);
+    assert_eq!(bins[0], (1..=MAX_METABALLS).collect::<Vec<_>>());
+    assert_eq!(limit_bins(&balls, &mut bins, 1, 1, 512.0, 512.0), 0);
+}
+
+#[wasm_bindgen_test]
+fn tile_grids_are_clamped() {
+    let mut effect = Effect::headless(Config::new());
+    effect.set_tile_grid(u32::MAX, 3);
+    let expected = format!("effect.set_tile_grid({}, 3);", MAX_TILES_PER_SIDE);
+    assert!(effect.export_as_code().contains(&expected));
+}
+
+#[wasm_bindgen_test]
+fn time_stays_continuous_across_speed_animations() {
+    let mut effect = Effect::headless(Config::new());
+    let dt = 0.01;
+    let mut now = 0.0;
+    let mut time = effect.update(now);
+    let mut last_step: Option<f64> = None;
+
+    effect.animate_speed(3.0, 0.5);
+    for frame in 0..100 {
+        if frame == 25 {
+            // retarget mid-transition
+            effect.animate_speed(0.5, 0.5);
+        }
+        now += dt;
+        let next = effect.update(now);
+        let step = (next - time) / dt;
+        if let Some(last_step) = last_step {
+            // the rate the time advances at never jumps
+            assert!((step - last_step).abs() < 0.2, "step {} after {}", step, last_step);
+        }
+        last_step = Some(step);
+        time = next;
+    }
+    assert!((last_step.unwrap() - 0.5).abs() < 1e-3);
+}
+
+#[wasm_bindgen_test]
+fn origin_modes_place_the_same_position_differently() {
+    assert_eq!(to_normalized(Origin::TopLeft, 0.25, 0.25, 1024.0, 512.0), (0.25, 0.25));
+    assert_eq!(to_normalized(Origin::Center, 0.0, 0.0, 1024.0, 512.0), (0.5, 0.5));
+    assert_eq!(to_normalized(Origin::Center, 0.25, 0.25, 1024.0, 512.0), (0.5625, 0.375));
+    assert_eq!(from_normalized(Origin::Center, 0.5625, 0.375, 1024.0, 512.0), (0.25, 0.25));
+
+    let mut effect = Effect::headless(Config::new());
+    effect.add_metaball(0.25, 0.25, 0.1).unwrap();
+    effect.set_origin(Origin::Center);
+    effect.add_metaball(0.25, 0.25, 0.1).unwrap();
+    let balls = effect.balls();
+    assert_eq!((balls[0].x, balls[0].y), (0.25, 0.25));
+    assert_eq!((balls[1].x, balls[1].y), (0.625, 0.375));
+}
+
+#[wasm_bindgen_test]
+fn blur_turns_a_hard_edge_into_a_gradient() {
+    use web_sys::WebGlRenderingContext as Gl;
+
+    assert!(gaussian_weights(0.0).is_empty());
+    let weights = gaussian_weights(4.0);
+    let sum = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+    assert!((sum - 1.0).abs() < 1e-5);
+
+    let canvas = create_canvas();
+    canvas.set_width(16);
+    canvas.set_height(2);
+    let context = canvas
+        .get_context("webgl")
+        .unwrap()
+        .unwrap()
+        .dyn_into::<Gl>()
+        .unwrap();
+    let mut resources = FrameResources::new(&context, 16, 2).unwrap();
+    resources.set_blur(&context, true).unwrap();
+    bind_quad(&context, &resources.quad);
+    let blur_pass = BlurPass::new(&context).unwrap();
+
+    // a black left half next to a white right half
+    resources.blur_scene().unwrap().bind(&context);
+    context.clear_color(0.0, 0.0, 0.0, 1.0);
+    context.clear(Gl::COLOR_BUFFER_BIT);
+    set_scissor(&context, Some(Region::new(8.0, 0.0, 8.0, 2.0)), 2.0);
+    context.clear_color(1.0, 1.0, 1.0, 1.0);
+    context.clear(Gl::COLOR_BUFFER_BIT);
+    set_scissor(&context, None, 2.0);
+    blur_pass.draw(&context, &resources, &weights);
+
+    let mut row = [0; 16 * 4];
+    context.read_pixels_with_opt_u8_array(0, 0, 16, 1, Gl::RGBA, Gl::UNSIGNED_BYTE, Some(&mut row)).unwrap();
+    let red: Vec<u8> = row.chunks(4).map(|pixel| pixel[0]).collect();
+    assert!(red[0] < 8 && red[15] > 247);
+    assert!(red[7] > 0 && red[8] < 255);
+    assert!(red.windows(2).all(|pair| pair[0] <= pair[1]));
+    let max_step = red.windows(2).map(|pair| pair[1] - pair[0]).max().unwrap();
+    assert!(max_step < 128);
+
+    // dropping the targets deletes them
+    let texture = resources.blur_scene().unwrap().texture.clone();
+    let framebuffer = resources.blur_scene().unwrap().framebuffer.clone();
+    resources.set_blur(&context, false).unwrap();
+    assert!(!context.is_texture(Some(&texture)));
+    assert!(!context.is_framebuffer(Some(&framebuffer)));
+}
+
+#[wasm_bindgen_test]
+fn large_deltas_are_sub_stepped() {
+    assert_eq!(substeps(0.01, 0.05), (1, 0.01));
+    let (count, step) = substeps(0.2, 0.05);
+    assert_eq!(count, 4);
+    assert!((step - 0.05).abs() < 1e-6);
+    assert_eq!(substeps(10.0, 0.05).0, MAX_SUBSTEPS);
+
+    let mut balls = vec![Metaball::new(0.5, 0.5, 0.1)];
+    balls[0].vx = 20.0;
+    assert_eq!(integrate(&mut balls, 0.2, 0.05), 4);
+    assert!(balls[0].x >= 0.0 && balls[0].x <= 1.0);
+}
+
+#[wasm_bindgen_test]
+fn field_image_is_bright_at_a_centered_ball() {
+    let mut effect = Effect::headless(Config::new());
+    effect.add_metaball(0.5, 0.5, 0.1).unwrap();
+
+    let (width, height) = (9, 9);
+    let data = effect.field_computer().to_grayscale(width, height);
+    assert_eq!(data.len(), (width * height * 4) as usize);
+
+    let gray = |column: u32, row: u32| data[((row * width + column) * 4) as usize];
+    assert_eq!(gray(4, 4), 255);
+    for &(column, row) in [(0, 0), (8, 0), (0, 8), (8, 8)].iter() {
+        assert!(gray(column, row) < 16);
+    }
+}
+
+#[wasm_bindgen_test]
+fn coalesced_samples_are_all_recorded() {
+    let mut pointer = PointerTracker::new();
+    pointer.push_all(&[(0.0, 0.0, 0.0), (1.0, 0.0, 0.01), (2.0, 0.0, 0.02)]);
+    assert_eq!(pointer.x, 2.0);
+    assert!((pointer.vx - 100.0).abs() < 1e-3);
+    assert_eq!(pointer.sample_count, 3);
+}
+
+#[wasm_bindgen_test]
+fn effects_sharing_a_clock_origin_stay_in_phase() {
+    let mut first = Effect::headless(Config::new());
+    let mut second = Effect::headless(Config::new());
+    // the first instance has been running for a while already
+    for frame in 1..50 {
+        first.update(frame as f64 * 0.016);
+    }
+    second.update(0.5);
+
+    first.set_shared_clock(-2.0);
+    second.set_shared_clock(-2.0);
+    assert!((first.update(1.0) - second.update(1.0)).abs() < 1e-9);
+    assert!((first.update(1.0) - 3.0).abs() < 1e-9);
+}
+
+#[wasm_bindgen_test]
+fn linked_clocks_stay_in_phase_across_throttled_gaps() {
+    let mut first = Effect::headless(Config::new());
+    let mut second = Effect::headless(Config::new());
+    first.set_shared_clock(0.0);
+    second.set_shared_clock(0.0);
+    // different refresh rates, then both throttled for seconds
+    for frame in 1..=60 {
+        first.update(frame as f64 / 60.0);
+    }
+    for frame in 1..=144 {
+        second.update(frame as f64 / 144.0);
+    }
+    first.update(4.5);
+    second.update(5.25);
+    assert!((first.update(6.0) - second.update(6.0)).abs() < 1e-9);
+    assert!((first.update(6.0) - 6.0).abs() < 1e-9);
+}
+
+#[wasm_bindgen_test]
+fn params_are_padded_to_the_uniform_array() {
+    let mut effect = Effect::headless(Config::new());
+    effect.set_params(&[0.25, 0.5]);
+    let params = effect.params();
+    assert_eq!(params.len(), MAX_PARAMS);
+    assert_eq!(&params[..3], &[0.25, 0.5, 0.0]);
+
+    effect.set_params(&vec![1.0; MAX_PARAMS + 4]);
+    assert_eq!(effect.params(), vec![1.0; MAX_PARAMS]);
+}
+
+#[wasm_bindgen_test]
+fn cel_shading_quantizes_into_bands() {
+    assert_eq!(quantize(0.1, 4), 0.0);
+    assert_eq!(quantize(0.3, 4), 1.0 / 3.0);
+    assert_eq!(quantize(0.99, 4), 1.0);
+    assert_eq!(quantize(1.0, 4), 1.0);
+    assert_eq!(quantize(0.49, 2), 0.0);
+    assert_eq!(quantize(0.5, 2), 1.0);
+    // 0 and 1 levels disable it
+    assert_eq!(quantize(0.37, 0), 0.37);
+    assert_eq!(quantize(0.37, 1), 0.37);
+}
+
+#[wasm_bindgen_test]
+fn captured_gl_state_is_restored() {
+    let context = create_canvas()
+        .get_context("webgl")
+        .unwrap()
+        .unwrap()
+        .dyn_into::<web_sys::WebGlRenderingContext>()
+        .unwrap();
+    context.enable(web_sys::WebGlRenderingContext::BLEND);
+
+    let saved = GlState::capture(&context);
+    context.disable(web_sys::WebGlRenderingContext::BLEND);
+    saved.restore(&context);
+    assert!(context.is_enabled(web_sys::WebGlRenderingContext::BLEND));
+}
+
+#[wasm_bindgen_test]
+fn the_hosts_position_attribute_pointer_is_restored() {
+    use web_sys::WebGlRenderingContext as Gl;
+
+    let context = create_canvas()
+        .get_context("webgl")
+        .unwrap()
+        .unwrap()
+        .dyn_into::<Gl>()
+        .unwrap();
+    let host_buffer = context.create_buffer().unwrap();
+    context.bind_buffer(Gl::ARRAY_BUFFER, Some(&host_buffer));
+    context.vertex_attrib_pointer_with_i32(0, 2, Gl::UNSIGNED_BYTE, true, 8, 4);
+
+    let saved = GlState::capture(&context);
+    // what a frame sets up for the quad
+    let quad = QuadBuffers::new(&context).unwrap();
+    context.bind_buffer(Gl::ARRAY_BUFFER, Some(&quad.position));
+    context.vertex_attrib_pointer_with_i32(0, 3, Gl::FLOAT, false, 0, 0);
+    saved.restore(&context);
+
+    let attrib = |name| context.get_vertex_attrib(0, name).unwrap();
+    let buffer: web_sys::WebGlBuffer = attrib(Gl::VERTEX_ATTRIB_ARRAY_BUFFER_BINDING).dyn_into().unwrap();
+    assert_eq!(buffer, host_buffer);
+    assert_eq!(attrib(Gl::VERTEX_ATTRIB_ARRAY_SIZE).as_f64(), Some(2.0));
+    assert_eq!(attrib(Gl::VERTEX_ATTRIB_ARRAY_TYPE).as_f64(), Some(Gl::UNSIGNED_BYTE as f64));
+    assert_eq!(attrib(Gl::VERTEX_ATTRIB_ARRAY_NORMALIZED).as_bool(), Some(true));
+    assert_eq!(attrib(Gl::VERTEX_ATTRIB_ARRAY_STRIDE).as_f64(), Some(8.0));
+    assert_eq!(context.get_vertex_attrib_offset(0, Gl::VERTEX_ATTRIB_ARRAY_POINTER), 4.0);
+    // the ARRAY_BUFFER binding is restored on its own
+    assert_eq!(context.get_parameter(Gl::ARRAY_BUFFER_BINDING).unwrap().dyn_into::<web_sys::WebGlBuffer>().unwrap(), host_buffer);
+}
+
+#[wasm_bindgen_test]
+fn tiny_balls_are_clamped_to_the_min_screen_radius() {
+    assert_eq!(effective_radius(0.01, 4.0, 100.0), 0.04);
+    assert_eq!(effective_radius(0.2, 4.0, 100.0), 0.2);
+
+    let mut effect = Effect::headless(Config::new());
+    effect.set_region_size(200.0, 100.0);
+    effect.add_metaball(0.5, 0.5, 0.01).unwrap();
+    effect.set_min_screen_radius(4.0);
+    assert_eq!(effect.frame_balls()[0].radius, 0.04);
+    // the stored radius is kept for when the resolution goes up again
+    assert_eq!(effect.balls()[0].radius, 0.01);
+
+    // pixel art renders 20 pixels across the shorter side
+    effect.set_pixel_art(40, 20);
+    assert_eq!(effect.frame_balls()[0].radius, 0.2);
+}
+
+#[wasm_bindgen_test]
+fn throttled_frame_gaps_dont_make_the_time_jump() {
+    let mut effect = Effect::headless(Config::new());
+    effect.add_metaball(0.5, 0.5, 0.1).unwrap();
+    effect.set_velocity(0, 0.1, 0.0).unwrap();
+    let mut now = 0.0;
+    for _ in 0..30 {
+        now += 1.0 / 60.0;
+        effect.update(now);
+    }
+    let time = effect.update(now);
+    let x = effect.balls()[0].x;
+
+    now += 2.0;
+    let resumed = effect.update(now);
+    assert!(resumed - time < 0.1, "time jumped from {} to {}", time, resumed);
+    assert!(effect.balls()[0].x - x < 0.01);
+    // The stall itself is still reported.
+    assert!(effect.stats().dropped_frames >= 100);
+}
+
+/// Output pixels across an edge at 0.3 scene units, upscaled 8x from samples
+/// of `low_res`, counting the ones that are neither inside nor outside.
+fn blurred_edge_pixels(low_res: impl Fn(f32) -> f32, to_coverage: impl Fn(f32) -> f32) -> usize {
+    let output_pixel = 0.01;
+    let low_res_pixel = output_pixel * 8.0;
+    (0..100)
+        .map(|i| i as f32 * output_pixel)
+        .map(|x| {
+            // bilinear filtering along one axis
+            let sample = x / low_res_pixel;
+            let t = sample.fract();
+            let value = low_res(sample.floor() * low_res_pixel) * (1.0 - t) + low_res((sample.floor() + 1.0) * low_res_pixel) * t;
+            to_coverage(value)
+        })
+        .filter(|&coverage| coverage > 0.05 && coverage < 0.95)
+        .count()
+}
+
+#[wasm_bindgen_test]
+fn crisp_upscaling_keeps_edges_sharp() {
+    let distance = |x: f32| 0.3 - x;
+    // one output pixel is 0.01 scene units on a 400 pixel region
+    let width = edge_width(400.0);
+
+    let upscaled = blurred_edge_pixels(|x| if distance(x) < 0.0 { 1.0 } else { 0.0 }, |value| value);
+    let crisp = blurred_edge_pixels(|x| edge_alpha(distance(x)), |alpha| crisp_coverage(alpha, width));
+    assert!(upscaled >= 6, "{} blurred pixels when upscaling", upscaled);
+    assert!(crisp <= 2, "{} blurred pixels with crisp edges", crisp);
+
+    assert_eq!(scaled_size(400.0, 300.0, 0.5), (200, 150));
+}
+
+#[wasm_bindgen_test]
+fn ellipses_have_an_anisotropic_field() {
+    let ellipse = Metaball::with_shape(0.5, 0.5, 0.05, Shape::Ellipse { rx: 0.2, ry: 0.05 });
+    let field = FieldComputer::new(&[ellipse], 100.0, 100.0);
+    // 0.15 off the center along each axis
+    assert!(field.is_inside_blob(0.65, 0.5));
+    assert!(!field.is_inside_blob(0.5, 0.65));
+    assert!(field.field_at(0.65, 0.5) > field.field_at(0.5, 0.65));
+
+    let capsule = Metaball::with_shape(0.5, 0.5, 0.05, Shape::Capsule { length: 0.4, angle: std::f32::consts::FRAC_PI_2 });
+    let field = FieldComputer::new(&[capsule], 100.0, 100.0);
+    assert!(field.is_inside_blob(0.5, 0.3));
+    assert!(!field.is_inside_blob(0.7, 0.5));
+}
+
+#[wasm_bindgen_test]
+fn painting_deposits_along_the_drag() {
+    let mut paint = Paint::new();
+    paint.drag_to(0.2, 0.5);
+    paint.drag_to(0.8, 0.5);
+    let dabs = paint.take_dabs();
+    let painted = |x: f32, y: f32| dabs.iter().map(|&dab| dab_value(dab, x, y, 100.0, 100.0)).sum::<f32>();
+    assert!(painted(0.5, 0.5) > DAB_INTENSITY);
+    assert!(painted(0.5, 0.9) < 1e-3);
+
+    // holding still doesn't keep depositing
+    paint.drag_to(0.8, 0.5);
+    assert!(paint.take_dabs().is_empty());
+}
+
+#[wasm_bindgen_test]
+fn gamma_brightens_mid_gray() {
+    assert_eq!(apply_gamma(0.5, 1.0), 0.5);
+    assert!((apply_gamma(0.5, 2.2) - 0.7297).abs() < 1e-4);
+    assert_eq!(apply_gamma(1.0, 2.2), 1.0);
+}
+
+#[wasm_bindgen_test]
+fn drags_keep_updating_outside_the_canvas() {
+    let canvas = create_canvas();
+    canvas.set_width(200);
+    canvas.set_height(100);
+    let document = web_sys::window().unwrap().document().unwrap();
+    // pointers can only be captured by connected elements
+    document.body().unwrap().append_child(&canvas).unwrap();
+    capture_pointer_during_drags(&canvas).unwrap();
+    let effect = Effect::headless(Config::new());
+    listen_to_pointer(&effect, PointerTarget::Canvas, &canvas, true).unwrap();
+    let pointer_event = |event_name: &str, buttons: u16, client_x: i32, client_y: i32| {
+        let init = web_sys::PointerEventInit::new();
+        init.set_pointer_id(1);
+        init.set_buttons(buttons);
+        init.set_client_x(client_x);
+        init.set_client_y(client_y);
+        web_sys::PointerEvent::new_with_event_init_dict(event_name, &init).unwrap()
+    };
+
+    // captures and releases without the pointer being pressed are ignored
+    canvas.dispatch_event(&pointer_event("pointerdown", 0, 0, 0)).unwrap();
+    assert!(!canvas.has_pointer_capture(1));
+
+    canvas.dispatch_event(&pointer_event("pointerdown", 1, 0, 0)).unwrap();
+    let captured = canvas.has_pointer_capture(1);
+
+    // the captured pointer left of and below the canvas still moves the effect
+    let rect = canvas.get_bounding_client_rect();
+    let (left, top) = (rect.left() as i32, rect.top() as i32);
+    canvas.dispatch_event(&pointer_event("pointermove", 1, left - 20, top + 120)).unwrap();
+    let pointer = effect.pointer();
+
+    canvas.dispatch_event(&pointer_event("pointerup", 0, left - 20, top + 120)).unwrap();
+    let released = !canvas.has_pointer_capture(1);
+    canvas.remove();
+
+    assert!(captured);
+    assert!(released);
+    assert_eq!(pointer.x, ((left - 20) as f64 - rect.left()) as f32);
+    assert_eq!(pointer.y, ((top + 120) as f64 - rect.top()) as f32);
+    let (x, y) = Region::new(0.0, 0.0, 200.0, 100.0).normalize(pointer.x, pointer.y);
+    assert!((x + 0.1).abs() < 0.01 && (y - 1.2).abs() < 0.01);
+}
+
+#[wasm_bindgen_test]
+fn ambient_balls_fill_an_empty_scene() {
+    let mut effect = Effect::headless(Config::new());
+    assert!(effect.frame_balls().is_empty());
+
+    effect.set_ambient_default(true);
+    effect.update(12.0);
+    let balls = effect.frame_balls();
+    assert_eq!(balls.len(), AMBIENT_BALL_COUNT);
+    assert_eq!(effect.ball_count(), 0);
+
+    let image = FieldComputer::new(&balls, 64.0, 64.0).to_grayscale(16, 16);
+    let min = image.iter().step_by(4).min().unwrap();
+    let max = image.iter().step_by(4).max().unwrap();
+    assert!(max > min);
+}
+
+#[wasm_bindgen_test]
+fn gpu_frame_time_is_reported_once_measured() {
+    let effect = Effect::headless(Config::new());
+    assert_eq!(effect.stats().gpu_frame_time_ms, None);
+
+    let mut stats = FrameStats::new();
+    stats.record_gpu_time(0.002);
+    stats.record_gpu_time(0.004);
+    assert!((stats.render_stats().gpu_frame_time_ms.unwrap() - 3.0).abs() < 1e-9);
+}
+
+#[wasm_bindgen_test]
+fn rims_take_the_edge_color() {
+    let red = (1.0, 0.0, 0.0);
+    let field = FieldComputer::new(&[Metaball::new(0.5, 0.5, 0.1)], 100.0, 100.0);
+    // just inside the isoline at 0.6, and at the center
+    let rim = surface_color(FILL_COLOR, red, field.distance_at(0.599, 0.5));
+    let core = surface_color(FILL_COLOR, red, field.distance_at(0.5, 0.5));
+    assert!(rim.0 > 0.99 && rim.1 < 0.01, "{:?}", rim);
+    assert_eq!(core, FILL_COLOR);
+}
+
+#[wasm_bindgen_test]
+fn positions_snap_to_grid_cell_centers() {
+    assert_eq!(snap_to_grid(0.3, 0.9, 4, 2), (0.375, 0.75));
+
+    let mut effect = Effect::headless(Config::new());
+    effect.add_metaball(0.3, 0.9, 0.1).unwrap();
+    effect.set_grid_snap(4, 2);
+    let ball = effect.frame_balls()[0];
+    assert_eq!((ball.x, ball.y), (0.375, 0.75));
+    assert_eq!(effect.balls()[0].x, 0.3);
+
+    effect.set_grid_snap(0, 2);
+    assert_eq!(effect.frame_balls()[0].x, 0.3);
+}
+
+#[wasm_bindgen_test]
+fn layer_orders_are_parsed_from_bottom_to_top() {
+    let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+    let (paint_over, warnings) = parse_layer_order(&names(&["blobs", "paint"])).unwrap();
+    assert!(warnings.is_empty());
+    let (paint_under, warnings) = parse_layer_order(&names(&["paint", "blobs", "paint", "bloom"])).unwrap();
+    assert_eq!(paint_under, vec![Layer::Paint, Layer::Blobs]);
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(paint_over, default_layer_order());
+    assert!(parse_layer_order(&names(&["paint"])).is_err());
+
+    let mut effect = Effect::headless(Config::new());
+    effect.set_layer_order(names(&["paint", "blobs"])).unwrap();
+    assert_eq!(effect.layer_order(), paint_under);
+}
+
+#[wasm_bindgen_test]
+fn pointer_tail_segments_lag_progressively() {
+    let mut effect = Effect::headless(Config::new());
+    effect.set_pointer_position(0.1, 0.5);
+    effect.set_pointer_tail(4, 0.1);
+    let mut now = 0.0;
+    for frame in 0..20 {
+        now += 1.0 / 60.0;
+        effect.set_pointer_position(0.1 + frame as f32 * 0.02, 0.5);
+        effect.update(now);
+    }
+
+    let segments: Vec<f32> = effect.frame_balls().iter().map(|ball| ball.x).collect();
+    assert_eq!(segments.len(), 4);
+    for pair in segments.windows(2) {
+        assert!(pair[0] > pair[1], "{:?}", segments);
+    }
+    assert!(segments[0] < 0.48);
+}
+
+#[wasm_bindgen_test]
+fn balls_near_the_cursor_melt() {
+    assert_eq!(melt_factor(0.0, 0.5, 0.2), 1.5);
+    assert_eq!(melt_factor(0.3, 0.5, 0.2), 1.0);
+
+    let mut effect = Effect::headless(Config::new());
+    effect.add_metaball(0.3, 0.5, 0.1).unwrap();
+    effect.add_metaball(0.9, 0.5, 0.1).unwrap();
+    effect.set_melt_on_hover(0.5, 0.2);
+    effect.set_pointer_position(0.32, 0.5);
+    let balls = effect.frame_balls();
+    assert!(balls[0].radius > 0.13);
+    assert_eq!(balls[1].radius, 0.1);
+}
+
+#[wasm_bindgen_test]
+fn pixel_art_renders_at_the_given_resolution() {
+    let mut effect = Effect::headless(Config::new());
+    effect.set_region_size(1280.0, 720.0);
+    assert_eq!(effect.render_size(), None);
+    effect.set_pixel_art(160, 90);
+    assert_eq!(effect.render_size(), Some(vec![160, 90]));
+
+    // every source pixel covers a block of 8 output pixels
+    let texels: Vec<u32> = (0..16).map(|pixel| nearest_texel(pixel, 1280, 160)).collect();
+    assert_eq!(texels, vec![0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1]);
+
+    effect.set_pixel_art(0, 90);
+    assert_eq!(effect.render_size(), None);
+}
+
+#[wasm_bindgen_test]
+fn faster_mouse_movement_grows_the_mouse_spheres() {
+    let scale_after_moving = |step: f32| {
+        let mut effect = Effect::headless(Config::new());
+        effect.set_mouse_speed_response(1.0);
+        let mut now = 0.0;
+        for frame in 0..30 {
+            now += 1.0 / 60.0;
+            effect.set_pointer_position(0.5 + frame as f32 * step, 0.5);
+            effect.update(now);
+        }
+        effect.mouse_ball_scale()
+    };
+    let still = scale_after_moving(0.0);
+    let slow = scale_after_moving(0.001);
+    let fast = scale_after_moving(0.01);
+    assert_eq!(still, 1.0);
+    assert!(slow > still);
+    assert!(fast > slow);
+}
+
+#[wasm_bindgen_test]
+fn fade_in_ramps_the_opacity_over_its_duration() {
+    let mut effect = Effect::headless(Config::new());
+    effect.set_fade_in(2.0);
+    effect.update(0.0);
+    assert!(effect.opacity() < 0.01);
+    effect.update(0.5);
+    effect.update(1.0);
+    let halfway = effect.opacity();
+    assert!(halfway > 0.0 && halfway < 1.0);
+    effect.update(1.5);
+    effect.update(2.0);
+    assert_eq!(effect.opacity(), 1.0);
+}
+
+#[wasm_bindgen_test]
+fn a_single_ball_traces_one_closed_isoline() {
+    let mut effect = Effect::headless(Config::new());
+    effect.set_region_size(200.0, 200.0);
+    effect.add_metaball(0.5, 0.5, 0.1).unwrap();
+    let path = effect.field_isolines_svg(1.0, 32);
+    assert_eq!(path.matches('M').count(), 1);
+    assert_eq!(path.matches('Z').count(), 1);
+    assert!(path.matches('L').count() > 8);
+
+    let field = effect.field_computer();
+    let contours = trace_contours(&field, 1.0, 32);
+    for &(x, y) in &contours[0].points {
+        assert!(field.distance_at(x, y).abs() < 0.05);
+    }
+}
+
+#[wasm_bindgen_test]
+fn time_of_day_moves_between_the_palettes() {
+    let mut effect = Effect::headless(Config::new());
+    assert_eq!(effect.palette(), DEFAULT_PALETTE);
+    effect.set_time_of_day(12.0);
+    assert_eq!(effect.palette(), DAY);
+    effect.set_time_of_day(0.0);
+    assert_eq!(effect.palette(), NIGHT);
+    effect.set_time_of_day(24.0);
+    assert_eq!(effect.palette(), NIGHT);
+
+    effect.set_time_of_day(3.0);
+    let blended = effect.palette().background;
+    assert!(blended.0 > NIGHT.background.0 && blended.0 < DAWN.background.0);
+    assert!(blended.2 > NIGHT.background.2 && blended.2 < DAWN.background.2);
+}
+
+#[wasm_bindgen_test]
+fn higher_merge_smoothness_fills_the_gap_between_balls() {
+    let field_between = |k: f32| {
+        let mut effect = Effect::headless(Config::new());
+        effect.set_region_size(100.0, 100.0);
+        effect.add_metaball(0.35, 0.5, 0.1).unwrap();
+        effect.add_metaball(0.65, 0.5, 0.1).unwrap();
+        effect.set_merge_smoothness(k);
+        effect.field_computer().field_at(0.5, 0.5)
+    };
+    let low = field_between(0.1);
+    let high = field_between(1.0);
+    assert!(low < THRESHOLD);
+    assert!(high > low);
+
+    // Away from the gap both agree with a single ball's surface.
+    let mut effect = Effect::headless(Config::new());
+    effect.set_region_size(100.0, 100.0);
+    effect.add_metaball(0.5, 0.5, 0.1).unwrap();
+    effect.set_merge_smoothness(0.5);
+    assert!((effect.field_computer().field_at(0.6, 0.5) - THRESHOLD).abs() < 0.01);
+}
+
+#[wasm_bindgen_test]
+fn a_wide_target_aspect_letterboxes_a_square_canvas() {
+    let square = Region::new(0.0, 0.0, 160.0, 160.0);
+    let content = square.letterboxed(16.0 / 9.0);
+    assert_eq!(content, Region::new(0.0, 35.0, 160.0, 90.0));
+    // The bars above and below are outside the rendered region, so they keep
+    // the background.
+    assert_eq!(content.y, square.height - content.y - content.height);
+    assert_eq!(content.normalize(80.0, 35.0), (0.5, 0.0));
+
+    assert_eq!(square.letterboxed(0.0), square);
+    assert_eq!(Region::new(0.0, 0.0, 200.0, 100.0).letterboxed(1.0), Region::new(50.0, 0.0, 100.0, 100.0));
+}
+
+#[wasm_bindgen_test]
+fn balls_are_removed_when_their_lifetime_runs_out() {
+    let mut effect = Effect::headless(Config::new());
+    effect.add_metaball(0.25, 0.5, 0.1).unwrap();
+    effect.add_metaball_with_lifetime(0.75, 0.5, 0.1, 0.5).unwrap();
+    effect.set_speed(0.25);
+    let mut radii = vec![];
+    for frame in 1..=9 {
+        effect.update(frame as f64 * 0.05);
+        radii.push(effect.frame_balls()[1].radius);
+    }
+    // Full size until the last quarter, then fading out.
+    assert_eq!(radii[0], 0.1);
+    assert!(radii.windows(2).all(|pair| pair[1] <= pair[0]));
+    assert!(*radii.last().unwrap() < 0.05);
+
+    effect.update(0.55);
+    assert_eq!(effect.ball_count(), 1);
+    assert_eq!(effect.balls()[0].x, 0.25);
+}
+
+#[wasm_bindgen_test]
+fn emitters_spawn_reproducible_balls_at_their_rate() {
+    let run = |seed: u32| {
+        let mut effect = Effect::headless(Config::new());
+        effect.set_seed(seed);
+        effect.add_emitter(0.5, 0.9, 10.0, 0.5, 0.3).unwrap();
+        for frame in 1..=60 {
+            effect.update(frame as f64 / 60.0);
+        }
+        effect.balls()
+    };
+    let balls = run(7);
+    assert_eq!(balls.len(), 10);
+    for ball in &balls {
+        assert!(ball.vy < 0.0);
+        assert!(ball.vx.abs() <= 0.3 * 0.25_f32.sin() + 1e-6);
+    }
+    assert_eq!(run(7), balls);
+    assert_ne!(run(8), balls);
+
+    let mut effect = Effect::headless(Config::new());
+    effect.add_emitter(0.5, 0.5, MAX_EMIT_RATE, 1.0, 0.1).unwrap();
+    effect.add_emitter(0.2, 0.5, MAX_EMIT_RATE, 1.0, 0.1).unwrap();
+    effect.update(0.1);
+    assert_eq!(effect.ball_count(), MAX_METABALLS);
+
+    assert!(check_emitter(0.5, 0.5, f32::INFINITY, 1.0, 0.1).is_err());
+    assert!(check_emitter(0.5, 0.5, MAX_EMIT_RATE * 2.0, 1.0, 0.1).is_err());
+    assert!(check_emitter(f32::NAN, 0.5, 10.0, 1.0, 0.1).is_err());
+    assert!(check_emitter(0.5, 0.5, 10.0, 1.0, f32::NAN).is_err());
+
+    // Only the balls that fit are built.
+    let mut emitter = Emitter::new(0.5, 0.5, MAX_EMIT_RATE, 1.0, 0.1);
+    assert_eq!(emitter.emit(1.0, 3, &mut Rng::new(DEFAULT_SEED)).len(), 3);
+}
+
+#[wasm_bindgen_test]
+fn head_on_collisions_exchange_velocities() {
+    let mut balls = vec![Metaball::new(0.3, 0.5, 0.1), Metaball::new(0.7, 0.5, 0.1)];
+    balls[0].vx = 0.5;
+    balls[1].vx = -0.2;
+    for _ in 0..60 {
+        integrate_with_collisions(&mut balls, 1.0 / 60.0, 1.0 / 60.0, Some(1.0));
+    }
+    assert!((balls[0].vx + 0.2).abs() < 1e-4);
+    assert!((balls[1].vx - 0.5).abs() < 1e-4);
+    assert_eq!(balls[0].vy, 0.0);
+    assert!(balls[1].x - balls[0].x >= 0.2 - COLLISION_SLOP - 1e-4);
+
+    // Resting contacts stay put.
+    let mut resting = vec![Metaball::new(0.4, 0.5, 0.1), Metaball::new(0.599, 0.5, 0.1)];
+    let before = resting.clone();
+    collide(&mut resting, 1.0);
+    assert_eq!(resting, before);
+}
+
+#[wasm_bindgen_test]
+fn the_bounding_box_encloses_every_blob() {
+    let mut effect = Effect::headless(Config::new());
+    effect.set_region_size(200.0, 100.0);
+    assert!(effect.blob_bounding_box().is_empty());
+
+    effect.add_metaball(0.3, 0.4, 0.1).unwrap();
+    effect.add_metaball(0.6, 0.5, 0.15).unwrap();
+    let bounds = effect.blob_bounding_box();
+    assert_eq!(bounds.len(), 4);
+    // Radii are fractions of the 100 pixel side, so half as wide horizontally.
+    assert!(bounds[0] <= 0.3 - 0.05 && bounds[2] >= 0.6 + 0.075);
+    assert!(bounds[1] <= 0.4 - 0.1 && bounds[3] >= 0.5 + 0.15);
+
+    let field = effect.field_computer();
+    for row in 0..50 {
+        for column in 0..50 {
+            let (x, y) = (column as f32 / 49.0, row as f32 / 49.0);
+            if field.is_inside_blob(x, y) {
+                assert!(x >= bounds[0] && x <= bounds[2] && y >= bounds[1] && y <= bounds[3]);
+            }
+        }
+    }
+}
+
+#[wasm_bindgen_test]
+fn spawns_within_the_cooldown_are_ignored() {
+    let mut effect = Effect::headless(Config::new());
+    assert!(!effect.spawn_at(0.5, 0.5, 0.0));
+
+    effect.set_spawn_on_click(0.05);
+    effect.set_spawn_cooldown(0.5);
+    assert!(effect.spawn_at(0.3, 0.5, 10.0));
+    assert!(!effect.spawn_at(0.4, 0.5, 10.2));
+    assert_eq!(effect.ball_count(), 1);
+    assert!(effect.spawn_at(0.4, 0.5, 10.5));
+    assert_eq!(effect.ball_count(), 2);
+
+    effect.set_spawn_cooldown(0.0);
+    assert!(effect.spawn_at(0.6, 0.5, 10.5));
+    assert_eq!(effect.balls()[2], Metaball::new(0.6, 0.5, 0.05));
+}
+
+#[wasm_bindgen_test]
+fn inverting_the_field_swaps_inside_and_outside() {
+    let mut effect = Effect::headless(Config::new());
+    effect.add_metaball(0.5, 0.5, 0.1).unwrap();
+    let field = effect.field_computer();
+    assert!(field.is_inside_blob(0.5, 0.5));
+    assert!(!field.is_inside_blob(0.9, 0.9));
+
+    effect.set_invert_field(true);
+    let field = effect.field_computer();
+    assert!(!field.is_inside_blob(0.5, 0.5));
+    assert!(field.is_inside_blob(0.9, 0.9));
+}
+
+#[wasm_bindgen_test]
+fn culling_front_faces_drops_the_whole_quad() {
+    use web_sys::WebGlRenderingContext as Gl;
+
+    assert_eq!(front_facing(&QUAD_POSITIONS, &QUAD_INDICES), vec![true, true]);
+
+    let canvas = create_canvas();
+    canvas.set_width(4);
+    canvas.set_height(4);
+    let context = canvas
+        .get_context("webgl")
+        .unwrap()
+        .unwrap()
+        .dyn_into::<Gl>()
+        .unwrap();
+    let program = init_program(&context, "precision mediump float; void main(void){ gl_FragColor = vec4(1.0); }").unwrap();
+    context.use_program(Some(&program));
+    let quad = QuadBuffers::new(&context).unwrap();
+    bind_quad(&context, &quad);
+
+    // the color of a pixel in each triangle after drawing over red
+    let drawn = |mode: CullMode| {
+        context.clear_color(1.0, 0.0, 0.0, 1.0);
+        context.clear(Gl::COLOR_BUFFER_BIT);
+        set_cull_mode(&context, mode);
+        draw_quad(&context);
+        set_cull_mode(&context, CullMode::None);
+        let pixel = |x: i32, y: i32| {
+            let mut pixel = [0; 4];
+            context.read_pixels_with_opt_u8_array(x, y, 1, 1, Gl::RGBA, Gl::UNSIGNED_BYTE, Some(&mut pixel)).unwrap();
+            pixel
+        };
+        (pixel(0, 3), pixel(3, 0))
+    };
+    let white = [255, 255, 255, 255];
+    let red = [255, 0, 0, 255];
+    assert_eq!(drawn(CullMode::None), (white, white));
+    assert_eq!(drawn(CullMode::Back), (white, white));
+    assert_eq!(drawn(CullMode::Front), (red, red));
+}
+
+#[wasm_bindgen_test]
+fn log_scaling_brightens_the_field_far_from_the_balls() {
+    let gray_at_corner = |log_scale: bool| {
+        let mut effect = Effect::headless(Config::new());
+        effect.add_metaball(0.5, 0.5, 0.1).unwrap();
+        effect.set_field_log_scale(log_scale);
+        let field = effect.field_computer();
+        (field.to_grayscale(4, 4)[0], field.is_inside_blob(0.5, 0.5), field.is_inside_blob(0.2, 0.2))
+    };
+    let (linear, linear_center, linear_outside) = gray_at_corner(false);
+    let (logarithmic, log_center, log_outside) = gray_at_corner(true);
+    assert!(linear < 20);
+    assert!(logarithmic > linear + 60);
+    assert_eq!((log_center, log_outside), (linear_center, linear_outside));
+}
+
+#[wasm_bindgen_test]
+fn self_test_passes_on_the_default_pipeline() {
+    let effect = Effect::headless(Config::new());
+    assert_eq!(effect.self_test(), Ok(()));
+}
+
+#[wasm_bindgen_test]
+fn mirrors_receive_every_frame_scaled_to_their_size() {
+    let source = create_canvas();
+    source.set_width(8);
+    source.set_height(8);
+    let context = source.get_context("2d").unwrap().unwrap()
+        .dyn_into::<web_sys::CanvasRenderingContext2d>().unwrap();
+    let mirrors: Vec<web_sys::HtmlCanvasElement> = [4, 32].iter()
+        .map(|&size| {
+            let mirror = create_canvas();
+            mirror.set_width(size);
+            mirror.set_height(size);
+            mirror
+        })
+        .collect();
+
+    let mirror_pixel = |mirror: &web_sys::HtmlCanvasElement| {
+        let context = mirror.get_context("2d").unwrap().unwrap()
+            .dyn_into::<web_sys::CanvasRenderingContext2d>().unwrap();
+        let last = mirror.width() as f64 - 1.0;
+        context.get_image_data(last, last, 1.0, 1.0).unwrap().data().to_vec()
+    };
+    for color in ["rgb(255, 0, 0)", "rgb(0, 0, 255)"] {
+        context.set_fill_style_str(color);
+        context.fill_rect(0.0, 0.0, 8.0, 8.0);
+        assert_eq!(mirror_canvas(&source, &mirrors), Ok(2));
+        let expected = if color.contains("255, 0, 0") { vec![255, 0, 0, 255] } else { vec![0, 0, 255, 255] };
+        for mirror in &mirrors {
+            assert_eq!(mirror_pixel(mirror), expected);
+        }
+    }
+
+    let mut effect = Effect::headless(Config::new());
+    assert!(effect.mirror_to(vec!["no-such-canvas".into()]).is_err());
+}
+
+#[wasm_bindgen_test]
+fn one_euro_filter_matches_the_reference_outputs() {
+    let smoothing = Smoothing::from_kind(SmoothingKind::OneEuro, 1.0, 0.1);
+    let mut filter = Filter::default();
+    let outputs: Vec<f32> = [0.0, 1.0, 1.0, 1.0, 5.0].iter()
+        .map(|&value| filter.filter(smoothing, value, 0.1))
+        .collect();
+    let reference = [0.0, 0.46546, 0.719668, 0.849875, 3.50784];
+    for (output, expected) in outputs.iter().zip(reference.iter()) {
+        assert!((output - expected).abs() < 1e-4, "{} != {}", output, expected);
+    }
+
+    let mut filter = Filter::default();
+    let exponential = Smoothing::from_kind(SmoothingKind::Exponential, 0.25, 0.0);
+    assert_eq!(filter.filter(exponential, 0.0, 0.1), 0.0);
+    assert_eq!(filter.filter(exponential, 1.0, 0.1), 0.25);
+
+    // The effect follows the smoothed position.
+    let mut effect = Effect::headless(Config::new());
+    effect.set_smoothing_algorithm(SmoothingKind::Exponential, 0.5, 0.0);
+    effect.set_pointer_position(0.0, 0.0);
+    effect.update(0.1);
+    effect.set_pointer_position(1.0, 1.0);
+    effect.update(0.2);
+    assert_eq!(effect.pointer_position(), (0.5, 0.5));
+}
+
+#[wasm_bindgen_test]
+fn scissored_clears_leave_the_rest_of_the_canvas() {
+    use web_sys::WebGlRenderingContext as Gl;
+
+    let canvas = create_canvas();
+    canvas.set_width(4);
+    canvas.set_height(4);
+    let context = canvas
+        .get_context("webgl")
+        .unwrap()
+        .unwrap()
+        .dyn_into::<Gl>()
+        .unwrap();
+    context.clear_color(1.0, 0.0, 0.0, 1.0);
+    context.clear(Gl::COLOR_BUFFER_BIT);
+
+    // The left half, from the top-left corner.
+    set_scissor(&context, Some(Region::new(0.0, 0.0, 2.0, 4.0)), 4.0);
+    context.clear_color(0.0, 0.0, 1.0, 1.0);
+    context.clear(Gl::COLOR_BUFFER_BIT);
+    set_scissor(&context, None, 4.0);
+    assert!(!context.is_enabled(Gl::SCISSOR_TEST));
+
+    let pixel = |x: i32| {
+        let mut pixel = [0; 4];
+        context.read_pixels_with_opt_u8_array(x, 1, 1, 1, Gl::RGBA, Gl::UNSIGNED_BYTE, Some(&mut pixel)).unwrap();
+        pixel
+    };
+    assert_eq!(pixel(0), [0, 0, 255, 255]);
+    assert_eq!(pixel(3), [255, 0, 0, 255]);
+
+    let region = Region::new(0.0, 0.0, 10.0, 10.0);
+    assert_eq!(region.intersect(&Region::new(5.0, 8.0, 10.0, 10.0)), Region::new(5.0, 8.0, 5.0, 2.0));
+}
+
+#[wasm_bindgen_test]
+fn color_pulses_flash_toward_the_accent_and_ease_back() {
+    let fill = (1.0, 1.0, 1.0);
+    let mut effect = Effect::headless(Config::new());
+    effect.update(0.0);
+    effect.trigger_color_pulse(1.0, 0.0, 0.0, 1.0);
+    let flashed = apply_pulse(fill, effect.pulse());
+    assert_eq!(flashed.0, 1.0);
+    assert!(flashed.1 < 0.5 && flashed.2 < 0.5);
+
+    effect.update(0.5);
+    let easing = apply_pulse(fill, effect.pulse());
+    assert!(easing.1 > flashed.1 && easing.1 < 1.0);
+
+    // A second pulse on top pulls further.
+    effect.trigger_color_pulse(1.0, 0.0, 0.0, 1.0);
+    assert!(apply_pulse(fill, effect.pulse()).1 < flashed.1);
+
+    effect.update(1.0);
+    effect.update(1.5);
+    assert_eq!(apply_pulse(fill, effect.pulse()), fill);
+
+    // Pulses run on the wall clock, so they end even while time stands still.
+    effect.set_speed(0.0);
+    effect.trigger_color_pulse(1.0, 0.0, 0.0, 1.0);
+    effect.update(2.0);
+    assert!(apply_pulse(fill, effect.pulse()).1 > flashed.1);
+    effect.update(2.5);
+    assert_eq!(apply_pulse(fill, effect.pulse()), fill);
+}
+
+#[wasm_bindgen_test]
+fn on_update_runs_each_frame_and_applies_its_values() {
+    let calls = || js_sys::Reflect::get(&js_sys::global(), &"onUpdateCalls".into()).unwrap().as_f64();
+    let mut effect = Effect::headless(Config::new());
+    effect.on_update(Some(js_sys::Function::new_with_args(
+        "time",
+        "globalThis.onUpdateCalls = (globalThis.onUpdateCalls || 0) + 1; return { speed: 2 };",
+    )));
+    effect.update(0.0);
+    assert_eq!(effect.update(0.5), 1.0);
+    assert_eq!(effect.update(1.0), 2.0);
+    assert_eq!(calls(), Some(3.0));
+
+    // A throwing callback is reported and the frame still runs.
+    effect.set_on_warning(Some(js_sys::Function::new_with_args("message", "globalThis.onUpdateWarning = message;")));
+    effect.on_update(Some(js_sys::Function::new_no_args("throw new Error('boom');")));
+    assert_eq!(effect.update(1.5), 3.0);
+    let warning = js_sys::Reflect::get(&js_sys::global(), &"onUpdateWarning".into()).unwrap();
+    assert_eq!(warning.as_string().as_deref(), Some("on_update threw: boom"));
+}
+
+#[wasm_bindgen_test]
+fn resizing_keeps_the_quad_buffers() {
+    use web_sys::WebGlRenderingContext as Gl;
+
+    let canvas = create_canvas();
+    let context = canvas
+        .get_context("webgl")
+        .unwrap()
+        .unwrap()
+        .dyn_into::<Gl>()
+        .unwrap();
+    let mut resources = FrameResources::new(&context, 4, 4).unwrap();
+    resources.set_blur(&context, true).unwrap();
+    let position = resources.quad.position.clone();
+    let index = resources.quad.index.clone();
+
+    resources.resize(&context, 8, 6).unwrap();
+    assert_eq!(resources.size(), (8, 6));
+    assert!(resources.is_blurring());
+    assert_eq!(resources.quad.position, position);
+    assert_eq!(resources.quad.index, index);
+}
+
+#[wasm_bindgen_test]
+fn invalid_balls_are_rejected_and_overlaps_stay_finite() {
+    let mut effect = Effect::headless(Config::new());
+    assert!(effect.add_metaball(0.5, 0.5, 0.0).is_err());
+    assert!(effect.add_metaball(f32::NAN, 0.5, 0.1).is_err());
+    assert!(effect.add_metaball_shape(0.5, 0.5, 0.1, ShapeKind::Ellipse, 0.0, 0.1).is_err());
+    assert_eq!(effect.ball_count(), 0);
+
+    let balls = [Metaball::new(0.5, 0.5, 0.1), Metaball::new(0.5, 0.5 + 1e-7, 0.1)];
+    let field = FieldComputer::new(&balls, 100.0, 100.0);
+    for &(x, y) in &[(0.5, 0.5), (0.5, 0.5 + 1e-7), (0.0, 0.0)] {
+        assert!(field.field_at(x, y).is_finite());
+        assert!(field.distance_at(x, y).is_finite());
+    }
+    let far = FieldComputer::new(&[Metaball::new(0.0, 0.0, 0.01)], 100.0, 100.0);
+    assert!(far.distance_at(1e6, 1e6).is_finite());
+}
+
+#[wasm_bindgen_test]
+fn non_finite_settings_are_ignored() {
+    let mut effect = Effect::headless(Config::new());
+    effect.set_speed(0.5);
+    effect.set_blur(2.0);
+    effect.set_ripple_params(0.1, 0.5, 2.0);
+    effect.set_max_physics_step(0.01);
+    let code = effect.export_as_code();
+
+    effect.set_speed(f32::NAN);
+    effect.animate_speed(f32::NAN, 1.0);
+    effect.animate_speed(1.0, f32::INFINITY);
+    effect.set_blur(f32::NAN);
+    effect.set_ripple_params(0.0, 0.5, 2.0);
+    effect.set_ripple_params(0.1, f32::INFINITY, 2.0);
+    effect.set_max_physics_step(f32::NAN);
+    assert_eq!(effect.export_as_code(), code);
+    assert!(effect.update(0.0).is_finite());
+    assert!(effect.update(0.1).is_finite());
+    assert!(gaussian_weights(f32::NAN).is_empty());
+}
+
+#[wasm_bindgen_test]
+fn keys_are_ignored_while_the_canvas_lacks_focus() {
+    let canvas = create_canvas();
+    let mut effect = Effect::headless(Config::new());
+    effect.attach_canvas(canvas.clone());
+    let space = || {
+        let init = web_sys::KeyboardEventInit::new();
+        init.set_key(" ");
+        init.set_cancelable(true);
+        web_sys::KeyboardEvent::new_with_keyboard_event_init_dict("keydown", &init).unwrap()
+    };
+    let scroll_up = || {
+        let init = web_sys::WheelEventInit::new();
+        init.set_delta_y(-100.0);
+        init.set_cancelable(true);
+        web_sys::WheelEvent::new_with_event_init_dict("wheel", &init).unwrap()
+    };
+    // Returns whether the effect kept the event from the page.
+    let dispatch = |event: &web_sys::Event| {
+        canvas.dispatch_event(event).unwrap();
+        event.default_prevented()
+    };
+
+    // Off by default, the page keeps the keyboard and the wheel.
+    assert!(!dispatch(&space()));
+    assert!(!dispatch(&scroll_up()));
+    assert!(!effect.export_as_code().contains("pause_physics"));
+    assert!(!effect.export_as_code().contains("set_speed"));
+
+    effect.set_require_focus(true);
+    assert_eq!(canvas.get_attribute("tabindex").as_deref(), Some("0"));
+    assert!(!dispatch(&space()));
+    assert!(!effect.export_as_code().contains("pause_physics"));
+
+    dispatch(&web_sys::Event::new("focus").unwrap());
+    assert!(dispatch(&space()));
+    assert!(effect.export_as_code().contains("effect.pause_physics(true);"));
+    assert!(dispatch(&scroll_up()));
+    let speed = format!("effect.set_speed({});", wheel_speed(1.0, -100.0));
+    assert!(effect.export_as_code().contains(&speed));
+    assert!(!effect.key_down("x"));
+
+    dispatch(&web_sys::Event::new("blur").unwrap());
+    assert!(!dispatch(&scroll_up()));
+    assert!(effect.export_as_code().contains(&speed));
+
+    effect.set_require_focus(false);
+    assert_eq!(canvas.get_attribute("tabindex"), None);
+}
+
+#[wasm_bindgen_test]
+fn test_mode_replays_runs_exactly() {
+    let run = || {
+        set_test_mode(true);
+        let mut effect = Effect::headless(Config::new());
+        effect.add_emitter(0.5, 0.9, 20.0, 1.0, 0.5).unwrap();
+        for frame in 1..=20 {
+            set_test_time(frame as f64 * 0.05);
+            effect.update(current_time());
+        }
+        effect.field_computer().to_grayscale(32, 32)
+    };
+    let first = run();
+    let second = run();
+
+    // Pointer samples are stamped with the injected time.
+    let canvas = create_canvas();
+    let effect = Effect::headless(Config::new());
+    listen_to_pointer(&effect, PointerTarget::Canvas, &canvas, false).unwrap();
+    set_test_time(2.5);
+    canvas.dispatch_event(&web_sys::MouseEvent::new("mousemove").unwrap()).unwrap();
+    let sample_time = effect.pointer().last_time();
+    // Time never runs backward.
+    set_test_time(0.0);
+    let now = current_time();
+    set_test_mode(false);
+
+    assert_eq!(second, first);
+    assert_eq!(sample_time, Some(2.5));
+    assert_eq!(now, 2.5);
+    assert_eq!(test_time(), None);
+}
+
+#[wasm_bindgen_test]
+fn centered_positions_use_the_canvas_aspect_before_the_first_frame() {
+    let canvas = create_canvas();
+    canvas.set_width(200);
+    canvas.set_height(100);
+    let mut effect = Effect::headless(Config::new());
+    effect.attach_canvas(canvas);
+    effect.set_origin(Origin::Center);
+    effect.add_metaball(0.25, 0.25, 0.1).unwrap();
+    let ball = effect.balls()[0];
+    assert_eq!((ball.x, ball.y), to_normalized(Origin::Center, 0.25, 0.25, 200.0, 100.0));
+    assert_ne!((ball.x, ball.y), to_normalized(Origin::Center, 0.25, 0.25, 1.0, 1.0));
+}